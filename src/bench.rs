@@ -0,0 +1,350 @@
+//! Repeated timing of the two phases of a solver. Allocations are only counted when the binary
+//! installs `CountingAllocator` as its global allocator, otherwise they read as zero.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::json::Value;
+use crate::json_object;
+use crate::runner::Solver;
+
+/// Differences in median time below this are noise rather than regressions.
+const NOISE: Duration = Duration::from_micros(20);
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+/// Wraps the system allocator, counting every allocation and the bytes requested.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Allocations {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+/// Totals since the program started.
+pub fn allocations() -> Allocations {
+    Allocations { count: ALLOCATIONS.load(Ordering::Relaxed), bytes: ALLOCATED.load(Ordering::Relaxed) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub min: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+}
+
+impl Stats {
+    /// Sorts the samples, which must not be empty. The median of an even number of samples is the
+    /// lower one and the 95th percentile uses the nearest rank.
+    pub fn new(samples: &mut [Duration]) -> Stats {
+        samples.sort();
+
+        let p95 = (samples.len() * 95).div_ceil(100).max(1) - 1;
+
+        Stats { min: samples[0], median: samples[(samples.len() - 1) / 2], p95: samples[p95] }
+    }
+}
+
+pub struct Phase {
+    pub time: Stats,
+    /// Average per iteration.
+    pub allocations: Allocations,
+}
+
+impl Phase {
+    fn new(samples: &mut [Duration], total: Allocations) -> Phase {
+        let iterations = samples.len() as u64;
+
+        Phase {
+            time: Stats::new(samples),
+            allocations: Allocations { count: total.count / iterations, bytes: total.bytes / iterations },
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json_object! {
+            "min_ns" => self.time.min.as_nanos() as u64,
+            "median_ns" => self.time.median.as_nanos() as u64,
+            "p95_ns" => self.time.p95.as_nanos() as u64,
+            "allocations" => self.allocations.count,
+            "allocated_bytes" => self.allocations.bytes,
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "min {:?}, median {:?}, p95 {:?}, {} allocations ({} bytes)",
+            self.time.min, self.time.median, self.time.p95, self.allocations.count, self.allocations.bytes)
+    }
+}
+
+pub struct Measurement {
+    pub day: u32,
+    pub part: u32,
+    pub name: Option<&'static str>,
+    /// Set by the caller when the input came from a profile.
+    pub profile: Option<String>,
+    pub iterations: usize,
+    pub parse: Phase,
+    pub solve: Phase,
+}
+
+fn key(day: u32, part: u32, name: Option<&str>, profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("day{} part{} {} [{}]", day, part, name.unwrap_or("default"), profile),
+        None => format!("day{} part{} {}", day, part, name.unwrap_or("default")),
+    }
+}
+
+/// Runs the generator and then the runner `iterations` times after one untimed warm-up.
+pub fn measure(solver: &Solver, input: &str, iterations: usize) -> Result<Measurement, String> {
+    let iterations = iterations.max(1);
+    let generated = (solver.generator)(input).map_err(|err| format!("failed while generating: {}", err))?;
+
+    (solver.runner)(&*generated).map_err(|err| format!("failed while running: {}", err))?;
+
+    let mut parse = Vec::with_capacity(iterations);
+    let mut solve = Vec::with_capacity(iterations);
+    let mut parse_allocations = Allocations::default();
+    let mut solve_allocations = Allocations::default();
+
+    for _ in 0..iterations {
+        let before = allocations();
+        let start = Instant::now();
+        let generated = (solver.generator)(input).map_err(|err| format!("failed while generating: {}", err))?;
+        parse.push(start.elapsed());
+        let between = allocations();
+
+        let start = Instant::now();
+        let answer = (solver.runner)(&*generated).map_err(|err| format!("failed while running: {}", err))?;
+        solve.push(start.elapsed());
+        let after = allocations();
+
+        drop(answer);
+
+        parse_allocations.count += between.count - before.count;
+        parse_allocations.bytes += between.bytes - before.bytes;
+        solve_allocations.count += after.count - between.count;
+        solve_allocations.bytes += after.bytes - between.bytes;
+    }
+
+    Ok(Measurement {
+        day: solver.day,
+        part: solver.part,
+        name: solver.name,
+        profile: None,
+        iterations,
+        parse: Phase::new(&mut parse, parse_allocations),
+        solve: Phase::new(&mut solve, solve_allocations),
+    })
+}
+
+impl Measurement {
+    /// How many times as long as `other` this took to solve, comparing medians.
+    pub fn relative_to(&self, other: &Measurement) -> f64 {
+        self.solve.time.median.as_secs_f64() / other.solve.time.median.as_secs_f64().max(1e-9)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json_object! {
+            "day" => self.day,
+            "part" => self.part,
+            "name" => self.name,
+            "profile" => self.profile.clone(),
+            "iterations" => self.iterations,
+            "parse" => self.parse.to_json(),
+            "solve" => self.solve.to_json(),
+        }
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Day {} - Part {}", self.day, self.part)?;
+
+        if let Some(name) = self.name {
+            write!(f, " - {}", name)?;
+        }
+
+        if let Some(profile) = &self.profile {
+            write!(f, " [{}]", profile)?;
+        }
+
+        writeln!(f, ": {} iterations\n\tparse: {}\n\tsolve: {}", self.iterations, self.parse, self.solve)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Recorded {
+    median: Duration,
+    allocations: u64,
+}
+
+/// Medians and allocation counts of an earlier run, read back from the JSON it was saved as.
+#[derive(Default)]
+pub struct Baseline {
+    phases: HashMap<(String, &'static str), Recorded>,
+}
+
+impl Baseline {
+    pub fn from_json(value: &Value) -> Result<Baseline, String> {
+        let mut baseline = Baseline::default();
+
+        for entry in value.as_array().ok_or("Baseline is not an array")? {
+            let number = |value: Option<&Value>, what: &str| {
+                value.and_then(Value::as_i64).and_then(|n| u32::try_from(n).ok()).ok_or_else(|| format!("Baseline entry without {}", what))
+            };
+            let day = number(entry.get("day"), "day")?;
+            let part = number(entry.get("part"), "part")?;
+            let name = entry.get("name").and_then(Value::as_str);
+            let profile = entry.get("profile").and_then(Value::as_str);
+
+            for phase in ["parse", "solve"] {
+                let stats = entry.get(phase).ok_or_else(|| format!("Baseline entry without {}", phase))?;
+                let median = stats.get("median_ns").and_then(Value::as_i64).ok_or("Baseline entry without median_ns")?;
+                let allocations = stats.get("allocations").and_then(Value::as_i64).ok_or("Baseline entry without allocations")?;
+
+                baseline.phases.insert((key(day, part, name, profile), phase), Recorded {
+                    median: Duration::from_nanos(median as u64),
+                    allocations: allocations as u64,
+                });
+            }
+        }
+
+        Ok(baseline)
+    }
+
+    /// Describes every phase whose median got slower by more than `threshold` (0.1 for 10%), or
+    /// that allocates more often than before. Solvers missing from the baseline are not compared.
+    pub fn regressions(&self, measurement: &Measurement, threshold: f64) -> Vec<String> {
+        let key = key(measurement.day, measurement.part, measurement.name, measurement.profile.as_deref());
+        let mut regressions = Vec::new();
+
+        for (phase, current) in [("parse", &measurement.parse), ("solve", &measurement.solve)] {
+            let recorded = match self.phases.get(&(key.clone(), phase)) {
+                Some(recorded) => *recorded,
+                None => continue,
+            };
+
+            let limit = recorded.median.mul_f64(1.0 + threshold).max(recorded.median + NOISE);
+
+            if current.time.median > limit {
+                let change = (current.time.median.as_secs_f64() / recorded.median.as_secs_f64().max(1e-9) - 1.0) * 100.0;
+
+                regressions.push(format!("{} {}: median {:?} -> {:?} (+{:.0}%)", key, phase, recorded.median, current.time.median, change));
+            }
+
+            if current.allocations.count > recorded.allocations {
+                regressions.push(format!("{} {}: allocations {} -> {}", key, phase, recorded.allocations, current.allocations.count));
+            }
+        }
+
+        regressions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{allocations, measure, Baseline, CountingAllocator, Stats};
+    use crate::solver;
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn parse(input: &str) -> Result<Vec<u64>, std::num::ParseIntError> {
+        input.split(',').map(str::parse).collect()
+    }
+
+    fn total(input: &str) -> Result<u64, std::num::ParseIntError> {
+        Ok(parse(input)?.iter().sum())
+    }
+
+    #[test]
+    fn stats() {
+        let mut samples = (1..=20).rev().map(Duration::from_millis).collect::<Vec<_>>();
+        let stats = Stats::new(&mut samples);
+
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.median, Duration::from_millis(10));
+        assert_eq!(stats.p95, Duration::from_millis(19));
+        assert_eq!(Stats::new(&mut [Duration::from_millis(3)]).p95, Duration::from_millis(3));
+    }
+
+    #[test]
+    fn counting() {
+        let before = allocations();
+        let buffer = vec![0u8; 1000];
+        let after = allocations();
+
+        assert!(after.count > before.count);
+        assert!(after.bytes - before.bytes >= buffer.len() as u64);
+    }
+
+    #[test]
+    fn relative() {
+        let solver = solver!(4, 1, None, total);
+        let mut slow = measure(&solver, "1,2,3", 1).unwrap();
+        let mut fast = measure(&solver, "1,2,3", 1).unwrap();
+
+        slow.solve.time.median = Duration::from_millis(30);
+        fast.solve.time.median = Duration::from_millis(10);
+
+        assert_eq!(slow.relative_to(&fast), 3.0);
+    }
+
+    #[test]
+    fn regressions() {
+        let solver = solver!(4, 1, None, total);
+        let measurement = measure(&solver, "1,2,3", 5).unwrap();
+
+        assert_eq!(measurement.iterations, 5);
+        assert!(measurement.solve.allocations.count >= 1);
+
+        let mut json = measurement.to_json();
+        let baseline = Baseline::from_json(&vec![json.clone()].into()).unwrap();
+
+        assert!(baseline.regressions(&measurement, 0.1).is_empty());
+
+        // A baseline that was much faster and never allocated
+        json.insert("solve", crate::json_object! { "median_ns" => 0, "allocations" => 0 });
+
+        let regressions = Baseline::from_json(&vec![json].into()).unwrap().regressions(&measurement, 0.1);
+
+        assert_eq!(regressions.len(), 1);
+        assert!(regressions[0].starts_with("day4 part1 default solve: allocations 0 -> "));
+        assert!(matches!(measure(&solver, "1,x", 5), Err(message) if message.starts_with("failed while running")));
+    }
+}
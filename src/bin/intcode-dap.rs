@@ -0,0 +1,9 @@
+use std::io;
+
+use advent_of_code::intcode::dap;
+
+fn main() -> io::Result<()> {
+    let stdout = io::stdout();
+
+    dap::serve(io::BufReader::new(io::stdin()), stdout.lock())
+}
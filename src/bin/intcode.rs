@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::process;
+
+use advent_of_code::cli::parse_value;
+use advent_of_code::intcode::disasm;
+use advent_of_code::intcode::dump::{self, Diff};
+use advent_of_code::intcode::gdb;
+use advent_of_code::intcode::repl::{self, Repl};
+use advent_of_code::intcode::replay::{Journal, Replay};
+use advent_of_code::intcode::{InputSource, NumberReader, NumberWriter, Process, Program, StopReason, Until};
+
+const USAGE: &str = "\
+Usage: intcode <command> <program> [options]
+       intcode repl [program]
+       intcode gdb <program> [--listen <addr> | --stdio]
+       intcode replay <program> <journal> [--listen <addr> | --stdio]
+
+Commands:
+    run     Run the program, reading inputs from stdin unless --input or --input-file is given
+    dump    Print memory one instruction per row, after running if inputs are given
+    diff    Run the program and print the cells that differ from its image
+    info    Print the metadata of an object file
+    repl    Explore a program interactively, type `help` inside for commands
+    gdb     Serve the program to GDB on standard input and output, or on a TCP address
+    replay  Re-run a recorded session and check it against its journal, under GDB if asked to
+
+Options:
+    --input <a,b,...>    Values to feed before running
+    --input-file <path>  File of comma or whitespace separated values, read after --input
+    --run                Run before dumping even without inputs
+    --ascii              Print outputs below 128 as characters
+    --trace              Print every instruction to stderr before it runs
+    --profile            Print the most executed instructions to stderr when done
+    --budget <n>         Stop after n instructions
+    --memory <n>         Stop once more than n cells are in use, overriding the object file
+    --record <path>      Write a journal of every input and output, for `intcode replay`";
+
+struct Options {
+    program: Program,
+    input: Vec<i64>,
+    input_file: Option<String>,
+    run: bool,
+    ascii: bool,
+    trace: bool,
+    profile: bool,
+    budget: Option<u64>,
+    memory: Option<usize>,
+    record: Option<String>,
+}
+
+fn load(path: Option<&String>) -> Result<Program, String> {
+    let path = path.ok_or("Missing program path")?;
+    let source = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+
+    source.trim().parse::<Program>().map_err(|e| format!("Cannot parse {}: {}", path, e))
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let program = load(args.first())?;
+
+        let mut options = Options {
+            program,
+            input: Vec::new(),
+            input_file: None,
+            run: false,
+            ascii: false,
+            trace: false,
+            profile: false,
+            budget: None,
+            memory: None,
+            record: None,
+        };
+        let mut args = args[1..].iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--input" => {
+                    let values = args.next().ok_or("Missing value for --input")?;
+                    options.input.extend(parse_values(values)?);
+                    options.run = true;
+                }
+                "--input-file" => {
+                    options.input_file = Some(args.next().ok_or("Missing value for --input-file")?.clone());
+                    options.run = true;
+                }
+                "--run" => options.run = true,
+                "--ascii" => options.ascii = true,
+                "--trace" => options.trace = true,
+                "--profile" => options.profile = true,
+                "--budget" => options.budget = Some(parse_value(args.next(), "--budget")?),
+                "--memory" => options.memory = Some(parse_value(args.next(), "--memory")?),
+                "--record" => options.record = Some(args.next().ok_or("Missing value for --record")?.clone()),
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn spawn(&self) -> Process {
+        let mut process = self.program.spawn();
+        process.track_writes();
+
+        for value in &self.input {
+            process.feed(*value);
+        }
+
+        process
+    }
+}
+
+fn parse_values(values: &str) -> Result<Vec<i64>, String> {
+    values.split(',').map(|v| v.trim().parse::<i64>().map_err(|e| format!("Invalid input {}: {}", v, e))).collect()
+}
+
+/// Runs to a stop and reports anything other than a clean halt on stderr.
+fn run(process: &mut Process) {
+    match process.run() {
+        StopReason::Halted => {}
+        reason => eprintln!("Stopped at {}: {:?}", process.eip(), reason),
+    }
+}
+
+fn dump(options: Options) {
+    let memory = if options.run {
+        let mut process = options.spawn();
+        run(&mut process);
+        process.memory()
+    } else {
+        options.program.code().to_vec()
+    };
+
+    print!("{}", dump::dump(&memory, &options.program.labels()));
+}
+
+fn diff(options: Options) {
+    let mut process = options.spawn();
+    run(&mut process);
+
+    let changes = dump::diff(options.program.code(), &process);
+
+    print!("{}", Diff { changes: &changes, process: &process });
+}
+
+fn run_program(options: Options) -> Result<(), String> {
+    let mut process = options.program.spawn();
+    let mut values = options.input.iter().copied();
+
+    if options.record.is_some() {
+        process.record();
+    }
+
+    let mut fallback: Option<NumberReader<Box<dyn BufRead>>> = match &options.input_file {
+        Some(path) => Some(NumberReader::new(Box::new(BufReader::new(fs::File::open(path).map_err(|e| format!("Cannot read {}: {}", path, e))?)))),
+        None if options.input.is_empty() => Some(NumberReader::new(Box::new(BufReader::new(io::stdin())))),
+        None => None,
+    };
+
+    let mut input = || values.next().or_else(|| fallback.as_mut().and_then(|f| f.next_input()));
+
+    let stdout = io::stdout();
+    let mut output = NumberWriter::new(stdout.lock());
+
+    if options.ascii {
+        output = output.ascii();
+    }
+
+    // Tracing and profiling need to see every instruction, so they run one step at a time
+    let stepping = options.trace || options.profile;
+    let mut profile = HashMap::new();
+
+    let reason = loop {
+        if options.budget.is_some_and(|budget| process.steps() >= budget) {
+            break StopReason::BudgetExhausted;
+        }
+
+        let eip = process.eip();
+        let steps = process.steps();
+
+        if options.trace {
+            let instruction = disasm::decode(&process, eip).map_or_else(|| format!("data {}", process.peek(eip)), |i| i.to_string());
+            eprintln!("{:>10} {:>6}  {}", steps, eip, instruction);
+        }
+
+        let budget = if stepping { Some(1) } else { options.budget.map(|budget| budget - steps) };
+        let memory = options.memory.or(options.program.metadata().memory_limit);
+        let reason = process.run_until_with(Until { budget, memory, ..Until::default() }, &mut input, &mut output).reason;
+
+        if stepping && process.steps() > steps {
+            *profile.entry(eip).or_insert(0u64) += 1;
+        }
+
+        if !stepping || reason != StopReason::BudgetExhausted {
+            break reason;
+        }
+    };
+
+    if options.profile {
+        let mut hot = profile.into_iter().collect::<Vec<_>>();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        eprintln!("{} instructions executed", process.steps());
+
+        for (eip, count) in hot.into_iter().take(20) {
+            let instruction = disasm::decode(&process, eip).map_or_else(|| "?".to_string(), |i| i.to_string());
+            eprintln!("{:>10} {:>6}  {}", count, eip, instruction);
+        }
+    }
+
+    if let (Some(path), Some(journal)) = (&options.record, process.journal()) {
+        fs::write(path, journal.to_string()).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+    }
+
+    match reason {
+        StopReason::Halted => Ok(()),
+        StopReason::WaitingForInput => match fallback.as_ref().and_then(|f| f.error()) {
+            Some(error) => Err(format!("Cannot read input at {}: {}", process.eip(), error)),
+            None => Err(format!("Ran out of input at {}", process.eip())),
+        },
+        StopReason::OutputClosed => Err(format!("Cannot write output at {}: {}", process.eip(), output.error().map_or_else(String::new, |e| e.to_string()))),
+        StopReason::BudgetExhausted => Err(format!("Instruction budget exhausted at {} after {} steps", process.eip(), process.steps())),
+        StopReason::MemoryLimit => Err(format!("Memory limit exceeded at {} with {} cells in use", process.eip(), process.memory_len())),
+        StopReason::Fault(fault) => Err(format!("Fault at {}: {}", process.eip(), fault)),
+        reason => Err(format!("Stopped at {}: {:?}", process.eip(), reason)),
+    }
+}
+
+fn info(options: Options) {
+    let metadata = options.program.metadata();
+
+    println!("cells   {}", options.program.code().len());
+    println!("entry   {}", metadata.entry);
+
+    if let Some(limit) = metadata.memory_limit {
+        println!("memory  {}", limit);
+    }
+
+    if !metadata.inputs.is_empty() {
+        println!("input   {}", metadata.inputs.join(", "));
+    }
+
+    if !metadata.outputs.is_empty() {
+        println!("output  {}", metadata.outputs.join(", "));
+    }
+
+    for (address, name) in &metadata.symbols {
+        let source = metadata.source_map.get(address).map(|l| format!("  ({})", l)).unwrap_or_default();
+        println!("symbol  {:>6}  {}{}", address, name, source);
+    }
+}
+
+fn interactive(args: &[String]) -> Result<(), String> {
+    let mut repl = Repl::new();
+
+    if let Some(path) = args.first() {
+        println!("{}", repl.execute(&format!("load {}", path)));
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    repl::serve(stdin.lock(), stdout.lock(), repl).map_err(|e| e.to_string())
+}
+
+/// Serves `process` to GDB as `flags` say, on standard input and output by default.
+fn serve(process: &mut Process, flags: &[String]) -> Result<(), String> {
+    let served = match flags.first().map(String::as_str) {
+        None | Some("--stdio") if flags.len() <= 1 => gdb::stdio(process),
+        Some("--listen") if flags.len() == 2 => {
+            eprintln!("Waiting for GDB on {}", flags[1]);
+            gdb::listen(process, flags[1].as_str())
+        }
+        Some("--listen") if flags.len() == 1 => return Err("Missing value for --listen".to_string()),
+        _ => return Err(format!("Unexpected arguments {}", flags.join(" "))),
+    };
+
+    served.map_err(|e| e.to_string())
+}
+
+fn debug(args: &[String]) -> Result<(), String> {
+    let mut process = load(args.first())?.spawn();
+
+    serve(&mut process, args.get(1..).unwrap_or(&[]))
+}
+
+fn replay(args: &[String]) -> Result<(), String> {
+    let program = load(args.first())?;
+    let path = args.get(1).ok_or("Missing journal path")?;
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let journal = text.parse::<Journal>().map_err(|e| format!("Cannot parse {}: {}", path, e))?;
+    let mut replay = Replay::new(&program, journal);
+
+    if args.len() > 2 {
+        serve(replay.process_mut(), &args[2..])?;
+
+        let replayed = replay.verify().map_err(|d| d.to_string())?;
+        eprintln!("Replayed {} of {} events", replayed, replay.journal().events.len());
+        return Ok(());
+    }
+
+    let reason = replay.run().map_err(|d| d.to_string())?;
+
+    for value in replay.outputs() {
+        println!("{}", value);
+    }
+
+    match reason {
+        StopReason::Halted => Ok(()),
+        reason => Err(format!("Stopped at {}: {}", replay.process().eip(), reason)),
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let command = match args.first() {
+        Some(command) => command.as_str(),
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match command {
+        "run" => Options::parse(&args[1..]).and_then(run_program),
+        "dump" => Options::parse(&args[1..]).map(dump),
+        "info" => Options::parse(&args[1..]).map(info),
+        "repl" => interactive(&args[1..]),
+        "gdb" => debug(&args[1..]),
+        "replay" => replay(&args[1..]),
+        "diff" => Options::parse(&args[1..]).map(diff),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("Unknown command {}\n\n{}", command, USAGE)),
+    };
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,10 @@
+//! Argument handling shared by the command line tools.
+
+use std::str::FromStr;
+
+/// Parses the value given after `flag`, failing with a message that names the flag when it is
+/// missing or does not parse.
+pub fn parse_value<T: FromStr>(value: Option<&String>, flag: &str) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value for {}", flag))?;
+    value.parse::<T>().map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
@@ -0,0 +1,217 @@
+//! Lazy iterators over orderings and selections of a slice. Every item is a fresh `Vec`, so the
+//! items only need to be `Clone`.
+
+/// Rearranges `values` into the next greater ordering. Returns `false`, leaving the values sorted
+/// ascending, once the last ordering has been passed.
+pub fn next_permutation<T: Ord>(values: &mut [T]) -> bool {
+    let pivot = match (1..values.len()).rev().find(|&i| values[i - 1] < values[i]) {
+        Some(i) => i - 1,
+        None => {
+            values.reverse();
+            return false;
+        }
+    };
+
+    let successor = (pivot + 1..values.len()).rev().find(|&i| values[i] > values[pivot]).unwrap();
+
+    values.swap(pivot, successor);
+    values[pivot + 1..].reverse();
+    true
+}
+
+/// Every ordering of the items using Heap's algorithm, which gets from one to the next with a
+/// single swap but visits them in no particular order.
+pub struct Permutations<T> {
+    items: Vec<T>,
+    counters: Vec<usize>,
+    position: usize,
+    started: bool,
+}
+
+pub fn permutations<T: Clone>(items: &[T]) -> Permutations<T> {
+    Permutations { items: items.to_vec(), counters: vec![0; items.len()], position: 1, started: false }
+}
+
+impl<T: Clone> Iterator for Permutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if !self.started {
+            self.started = true;
+            return Some(self.items.clone());
+        }
+
+        while self.position < self.items.len() {
+            let i = self.position;
+
+            if self.counters[i] < i {
+                let j = if i.is_multiple_of(2) { 0 } else { self.counters[i] };
+
+                self.items.swap(j, i);
+                self.counters[i] += 1;
+                self.position = 1;
+
+                return Some(self.items.clone());
+            }
+
+            self.counters[i] = 0;
+            self.position += 1;
+        }
+
+        None
+    }
+}
+
+/// Every ordering of the items, in lexicographic order of their positions in the slice. For a
+/// sorted slice that is plain lexicographic order.
+pub struct LexicographicPermutations<T> {
+    items: Vec<T>,
+    indices: Option<Vec<usize>>,
+}
+
+pub fn lexicographic_permutations<T: Clone>(items: &[T]) -> LexicographicPermutations<T> {
+    LexicographicPermutations { items: items.to_vec(), indices: Some((0..items.len()).collect()) }
+}
+
+impl<T: Clone> Iterator for LexicographicPermutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let items = &self.items;
+        let indices = self.indices.as_mut()?;
+        let result = indices.iter().map(|&i| items[i].clone()).collect();
+
+        if !next_permutation(indices) {
+            self.indices = None;
+        }
+
+        Some(result)
+    }
+}
+
+/// Every selection of `k` items, keeping their order from the slice.
+pub struct Combinations<T> {
+    items: Vec<T>,
+    indices: Option<Vec<usize>>,
+}
+
+pub fn combinations<T: Clone>(items: &[T], k: usize) -> Combinations<T> {
+    let indices = if k <= items.len() { Some((0..k).collect()) } else { None };
+
+    Combinations { items: items.to_vec(), indices }
+}
+
+impl<T: Clone> Iterator for Combinations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let n = self.items.len();
+        let items = &self.items;
+        let indices = self.indices.as_mut()?;
+        let k = indices.len();
+        let result = indices.iter().map(|&i| items[i].clone()).collect();
+
+        match (0..k).rev().find(|&i| indices[i] != i + n - k) {
+            Some(i) => {
+                indices[i] += 1;
+
+                for j in i + 1..k {
+                    indices[j] = indices[j - 1] + 1;
+                }
+            }
+            None => self.indices = None,
+        }
+
+        Some(result)
+    }
+}
+
+/// Every way of picking one item from each set, the last set changing fastest.
+pub struct Product<T> {
+    sets: Vec<Vec<T>>,
+    indices: Option<Vec<usize>>,
+}
+
+pub fn product<T: Clone, S: AsRef<[T]>>(sets: &[S]) -> Product<T> {
+    let sets = sets.iter().map(|set| set.as_ref().to_vec()).collect::<Vec<_>>();
+    let indices = if sets.iter().all(|set| !set.is_empty()) { Some(vec![0; sets.len()]) } else { None };
+
+    Product { sets, indices }
+}
+
+impl<T: Clone> Iterator for Product<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let sets = &self.sets;
+        let indices = self.indices.as_mut()?;
+        let result = indices.iter().zip(sets).map(|(&i, set)| set[i].clone()).collect();
+
+        let carried = (0..indices.len()).rev().find(|&i| {
+            indices[i] += 1;
+
+            if indices[i] < sets[i].len() {
+                return true;
+            }
+
+            indices[i] = 0;
+            false
+        });
+
+        if carried.is_none() {
+            self.indices = None;
+        }
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{combinations, lexicographic_permutations, next_permutation, permutations, product};
+
+    #[test]
+    fn heap() {
+        let all = permutations(&['a', 'b', 'c']).collect::<Vec<_>>();
+
+        assert_eq!(all.len(), 6);
+        assert_eq!(all.iter().collect::<HashSet<_>>().len(), 6);
+        assert_eq!(all[0], vec!['a', 'b', 'c']);
+        assert_eq!(permutations::<u8>(&[]).count(), 1);
+    }
+
+    #[test]
+    fn lexicographic() {
+        let all = lexicographic_permutations(&[1, 2, 3]).collect::<Vec<_>>();
+
+        assert_eq!(all, vec![vec![1, 2, 3], vec![1, 3, 2], vec![2, 1, 3], vec![2, 3, 1], vec![3, 1, 2], vec![3, 2, 1]]);
+
+        let mut values = [1, 1, 2];
+        let mut count = 1;
+
+        while next_permutation(&mut values) {
+            count += 1;
+        }
+
+        assert_eq!(count, 3);
+        assert_eq!(values, [1, 1, 2]);
+    }
+
+    #[test]
+    fn selections() {
+        assert_eq!(combinations(&[1, 2, 3, 4], 2).collect::<Vec<_>>(), vec![vec![1, 2], vec![1, 3], vec![1, 4], vec![2, 3], vec![2, 4], vec![3, 4]]);
+        assert_eq!(combinations(&[1, 2], 0).collect::<Vec<_>>(), vec![Vec::<i32>::new()]);
+        assert_eq!(combinations(&[1, 2], 3).count(), 0);
+    }
+
+    #[test]
+    fn products() {
+        assert_eq!(product(&[vec![0, 1], vec![5, 6, 7]]).collect::<Vec<_>>(), vec![
+            vec![0, 5], vec![0, 6], vec![0, 7], vec![1, 5], vec![1, 6], vec![1, 7],
+        ]);
+        assert_eq!(product(&[vec![1], vec![]]).count(), 0);
+        assert_eq!(product::<i32, Vec<i32>>(&[]).count(), 1);
+    }
+}
@@ -1,13 +1,7 @@
-use std::num::ParseIntError;
+use crate::error::{number, parse_lines, ParseError};
 
-use aoc_runner_derive::aoc;
-
-#[aoc(day1, part1)]
-pub fn part1(input: &str) -> Result<u64, ParseIntError> {
-    Ok(input
-        .split_whitespace()
-        .map(|mass| mass.parse::<u64>())
-        .collect::<Result<Vec<_>, _>>()?
+pub fn part1(input: &str) -> Result<u64, ParseError> {
+    Ok(parse_lines(input, number::<u64>)?
         .iter()
         .map(|mass| (mass / 3) - 2)
         .sum())
@@ -22,13 +16,38 @@ fn fuel_cost(mass: u64) -> u64 {
     }
 }
 
-#[aoc(day1, part2)]
-pub fn part2(input: &str) -> Result<u64, ParseIntError> {
-    Ok(input
-        .split_whitespace()
-        .map(|mass| mass.parse::<u64>())
-        .collect::<Result<Vec<_>, _>>()?
+pub fn part2(input: &str) -> Result<u64, ParseError> {
+    Ok(parse_lines(input, number::<u64>)?
         .iter()
         .map(|mass| fuel_cost(*mass))
         .sum())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::generate::{self, Rng};
+
+    /// Adds fuel for the fuel one step at a time until it runs out.
+    fn total_fuel(mass: i64) -> i64 {
+        let mut total = 0;
+        let mut fuel = mass / 3 - 2;
+
+        while fuel > 0 {
+            total += fuel;
+            fuel = fuel / 3 - 2;
+        }
+
+        total
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..50 {
+            let input = generate::masses(&mut Rng::new(seed), 100);
+            let masses = input.lines().map(|line| line.parse::<i64>().unwrap()).collect::<Vec<_>>();
+
+            assert_eq!(super::part1(&input), Ok(masses.iter().map(|mass| mass / 3 - 2).sum::<i64>() as u64), "seed {}", seed);
+            assert_eq!(super::part2(&input), Ok(masses.iter().map(|mass| total_fuel(*mass)).sum::<i64>() as u64), "seed {}", seed);
+        }
+    }
+}
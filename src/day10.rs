@@ -1,8 +1,12 @@
 use std::f64::consts::{FRAC_PI_2, PI};
-use std::num::ParseIntError;
+use std::fmt;
 use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
+use crate::error::{parse_lines, ErrorKind, ParseError, SolveError};
+use crate::json::Value;
+use crate::json_object;
+use crate::runner::Solution;
+use crate::svg::Svg;
 
 fn gcd(mut a: usize, mut b: usize) -> usize {
     assert!(a != 0 || b != 0);
@@ -20,50 +24,68 @@ fn gcd(mut a: usize, mut b: usize) -> usize {
     a
 }
 
-#[derive(PartialEq)]
-enum Position {
+/// One cell of the asteroid field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
     Empty,
     Asteroid,
 }
 
 impl Position {
-    fn has_asteroid(&self) -> bool {
+    pub fn has_asteroid(&self) -> bool {
         self == &Position::Asteroid
     }
 }
 
 impl Position {
-    fn from_char(c: char) -> Position {
+    fn from_char(c: char) -> Result<Position, ParseError> {
         match c {
-            '.' => Position::Empty,
-            '#' => Position::Asteroid,
-            _ => panic!("Invalid position: {}", c),
+            '.' => Ok(Position::Empty),
+            '#' => Ok(Position::Asteroid),
+            _ => Err(ParseError::new(ErrorKind::UnexpectedChar(c))),
         }
     }
 }
 
-struct Map(Vec<Vec<Position>>);
+/// An asteroid field, addressed by `(x, y)` with `y` growing downwards.
+pub struct Map(Vec<Vec<Position>>);
 
 impl FromStr for Map {
-    type Err = ParseIntError;
+    type Err = ParseError;
 
-    fn from_str(s: &str) -> Result<Map, ParseIntError> {
-        Ok(Map(s.split_whitespace().map(|line| line.chars().map(Position::from_char).collect()).collect()))
+    fn from_str(s: &str) -> Result<Map, ParseError> {
+        parse_lines(s, |line| line.chars().enumerate().map(|(x, c)| Position::from_char(c).map_err(|err| err.at(1, x + 1))).collect()).map(Map)
     }
 }
 
 impl Map {
-    fn asteroids(&self) -> Vec<(usize, usize)> {
+    pub fn width(&self) -> usize {
+        self.0.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Every asteroid, row by row.
+    pub fn asteroids(&self) -> Vec<(usize, usize)> {
         self.0.iter().enumerate().flat_map(|(y, row)| row.iter().enumerate().filter(|(_, p)| p.has_asteroid()).map(move |(x, _)| (x, y))).collect()
     }
 
-    fn has_asteroid_at(&self, pos: (usize, usize)) -> bool {
-        self.0[pos.1][pos.0].has_asteroid()
+    /// Whether there is an asteroid at `pos`, which is never the case outside the field.
+    pub fn has_asteroid_at(&self, pos: (usize, usize)) -> bool {
+        self.0.get(pos.1).and_then(|row| row.get(pos.0)).is_some_and(Position::has_asteroid)
+    }
+
+    /// Whether nothing blocks the line of sight between two places. A place always sees itself.
+    pub fn can_see(&self, lhs: (usize, usize), rhs: (usize, usize)) -> bool {
+        intermidiates(lhs, rhs).iter().all(|pos| !self.has_asteroid_at(*pos))
     }
 
-    fn best_location(&self) -> ((usize, usize), usize) {
+    /// The asteroid that sees the most others, the first one on ties, or nothing on an empty field.
+    pub fn best_location(&self) -> Option<Station> {
         let asteroids = self.asteroids();
-        let mut result = ((0, 0), 0);
+        let mut result = Option::<Station>::None;
 
         for asteroid in &asteroids {
             let mut reachable = 0;
@@ -71,43 +93,102 @@ impl Map {
             for other in &asteroids {
                 if asteroid == other { continue; }
 
-                if can_see_asteroid(&self, *asteroid, *other) {
+                if self.can_see(*asteroid, *other) {
                     reachable += 1;
                 }
             }
 
-            if reachable > result.1 {
-                result = (*asteroid, reachable);
+            if result.is_none_or(|best| reachable > best.visible) {
+                result = Some(Station { x: asteroid.0, y: asteroid.1, visible: reachable });
             }
         }
 
         result
     }
 
-    fn blast_asteroid(&mut self, pos: (usize, usize)) {
-        self.0[pos.1][pos.0] = Position::Empty;
+    /// Removes the asteroid at `pos` and returns whether there was one, which is never the case
+    /// outside the field.
+    pub fn blast_asteroid(&mut self, pos: (usize, usize)) -> bool {
+        match self.0.get_mut(pos.1).and_then(|row| row.get_mut(pos.0)) {
+            Some(position) => std::mem::replace(position, Position::Empty).has_asteroid(),
+            None => false,
+        }
+    }
+
+    /// Every other asteroid in the order a laser at `station` vaporizes them, with the rotation of
+    /// the laser that gets each one, counting from 1.
+    pub fn vaporization_order(&self, station: (usize, usize)) -> Vec<((usize, usize), usize)> {
+        let mut map = Map(self.0.clone());
+        let mut queue = self.asteroids().into_iter().filter(|pos| *pos != station).map(|pos| (pos, direction(station, pos))).collect::<Vec<_>>();
+        let mut order = Vec::with_capacity(queue.len());
+        let mut rotations = 0;
+
+        queue.sort_by(|(_, lhs), (_, rhs)| f64::partial_cmp(lhs, rhs).unwrap());
+
+        while !queue.is_empty() {
+            rotations += 1;
+
+            let mut marked = Vec::<(usize, usize)>::new();
+
+            queue.retain(|(pos, _)| {
+                if !map.can_see(station, *pos) { return true; }
+
+                marked.push(*pos);
+                false
+            });
+
+            for asteroid in marked {
+                map.blast_asteroid(asteroid);
+                order.push((asteroid, rotations));
+            }
+        }
+
+        order
     }
 }
 
 fn intermidiates(lhs: (usize, usize), rhs: (usize, usize)) -> Vec<(usize, usize)> {
-    assert_ne!(lhs, rhs);
+    if lhs == rhs {
+        return Vec::new();
+    }
 
     let delta = (rhs.0 as isize - lhs.0 as isize, rhs.1 as isize - lhs.1 as isize);
-    let divider = gcd(delta.0.abs() as usize, delta.1.abs() as usize);
+    let divider = gcd(delta.0.unsigned_abs(), delta.1.unsigned_abs());
     let step = (delta.0 / (divider as isize), delta.1 / (divider as isize));
 
     (1..divider).map(|i| ((lhs.0 as isize + step.0 * i as isize) as usize, (lhs.1 as isize + step.1 * i as isize) as usize)).collect()
 }
 
-fn can_see_asteroid(map: &Map, lhs: (usize, usize), rhs: (usize, usize)) -> bool {
-    intermidiates(lhs, rhs).iter().all(|pos| !map.has_asteroid_at(*pos))
+/// The best place for the monitoring station and how many asteroids it sees from there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Station {
+    pub x: usize,
+    pub y: usize,
+    pub visible: usize,
 }
 
-#[aoc(day10, part1)]
-pub fn part1(input: &str) -> Result<usize, ParseIntError> {
-    let map = input.parse::<Map>()?;
+impl fmt::Display for Station {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.visible)
+    }
+}
+
+impl Solution for Station {
+    fn report(&self) -> Option<Value> {
+        Some(json_object! { "x" => self.x, "y" => self.y, "visible" => self.visible })
+    }
+}
+
+fn no_asteroids() -> SolveError {
+    SolveError::Unsolvable("the map has no asteroids".to_string())
+}
 
-    Ok(map.best_location().1)
+pub fn station(input: &str) -> Result<Station, SolveError> {
+    input.parse::<Map>()?.best_location().ok_or_else(no_asteroids)
+}
+
+pub fn part1(input: &str) -> Result<usize, SolveError> {
+    station(input).map(|station| station.visible)
 }
 
 fn direction(base: (usize, usize), pos: (usize, usize)) -> f64 {
@@ -117,46 +198,77 @@ fn direction(base: (usize, usize), pos: (usize, usize)) -> f64 {
     if atan2 < FRAC_PI_2 { atan2 + FRAC_PI_2 + PI } else { atan2 - FRAC_PI_2 }
 }
 
-#[aoc(day10, part2)]
-pub fn part2(input: &str) -> Result<usize, ParseIntError> {
-    let mut map = input.parse::<Map>()?;
-    let base = map.best_location().0;
-    let asteroids = map.asteroids();
+/// The 200th asteroid the laser vaporizes, and how many turns it takes to get there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vaporization {
+    pub station: (usize, usize),
+    pub asteroid: (usize, usize),
+    pub rotations: usize,
+}
 
-    let mut destroyed = 0;
-    let mut queue = asteroids.iter().filter(|pos| **pos != base).map(|pos| (*pos, direction(base, *pos))).collect::<Vec<_>>();
-    let mut result = Option::<usize>::None;
+impl fmt::Display for Vaporization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.asteroid.0 * 100 + self.asteroid.1)
+    }
+}
 
-    queue.sort_by(|(_, lhs), (_, rhs)| f64::partial_cmp(lhs, rhs).unwrap());
+impl Solution for Vaporization {
+    fn report(&self) -> Option<Value> {
+        Some(json_object! {
+            "station" => json_object! { "x" => self.station.0, "y" => self.station.1 },
+            "asteroid" => json_object! { "x" => self.asteroid.0, "y" => self.asteroid.1 },
+            "rotations" => self.rotations,
+        })
+    }
+}
 
-    while result == None {
-        assert!(queue.len() > 0);
+pub fn vaporization(input: &str) -> Result<Vaporization, SolveError> {
+    let map = input.parse::<Map>()?;
+    let best = map.best_location().ok_or_else(no_asteroids)?;
+    let station = (best.x, best.y);
+    let order = map.vaporization_order(station);
+    let (asteroid, rotations) = *order.get(199)
+        .ok_or_else(|| SolveError::Unsolvable(format!("only {} other asteroids to vaporize", order.len())))?;
 
-        let mut marked = Vec::<(usize, usize)>::new();
+    Ok(Vaporization { station, asteroid, rotations })
+}
 
-        queue.retain(|(pos, _)| {
-            if !can_see_asteroid(&map, base, *pos) { return true; }
+pub fn part2(input: &str) -> Result<usize, SolveError> {
+    vaporization(input).map(|vaporization| vaporization.asteroid.0 * 100 + vaporization.asteroid.1)
+}
 
-            marked.push(*pos);
-            destroyed += 1;
+/// Draws the asteroids with the monitoring station in red and a ray to every asteroid it can see.
+pub fn render(input: &str) -> Result<Svg, SolveError> {
+    let map = input.parse::<Map>()?;
+    let asteroids = map.asteroids();
+    let best = map.best_location().ok_or_else(no_asteroids)?;
+    let (station, visible) = ((best.x, best.y), best.visible);
 
-            if destroyed == 200 {
-                result = Some(pos.0 * 100 + pos.1);
-            }
+    let mut svg = Svg::new((-1.0, -1.0), (map.width() as f64 + 1.0, map.height() as f64 + 1.0));
+    let center = |pos: (usize, usize)| (pos.0 as f64, pos.1 as f64);
 
-            false
-        });
+    svg.title(&format!("Day 10: station at {},{} sees {} of {} asteroids", station.0, station.1, visible, asteroids.len()));
+    svg.background("black");
 
-        for asteroid in marked {
-            map.blast_asteroid(asteroid);
-        }
+    for asteroid in asteroids.iter().filter(|asteroid| **asteroid != station && map.can_see(station, **asteroid)) {
+        svg.line(center(station), center(*asteroid), r#"stroke="gold" stroke-width="0.05" stroke-opacity="0.6""#);
+    }
+
+    for asteroid in &asteroids {
+        let style = if *asteroid == station { r#"fill="red""# } else { r#"fill="lightgray""# };
+
+        svg.circle(center(*asteroid), 0.3, style);
     }
 
-    Ok(result.unwrap())
+    Ok(svg)
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeMap;
+
+    use crate::generate::{self, Rng};
+
     #[test]
     fn intermidiates() {
         assert_eq!(super::intermidiates((0, 0), (2, 2)), vec![(1, 1)]);
@@ -188,4 +300,99 @@ mod test {
     fn part2() {
         assert_eq!(super::part2(".#..##.###...#######\n##.############..##.\n.#.######.########.#\n.###.#######.####.#.\n#####.##.#.##.###.##\n..#####..#.#########\n####################\n#.####....###.#.#.##\n##.#################\n#####.##.###..####..\n..######..##.#######\n####.##.####...##..#\n.#####..#.######.###\n##...#.##########...\n#.##########.#######\n.####.#.###.###.#.##\n....##.##.###..#####\n.#.#.###########.###\n#.#.#.#####.####.###\n###.##.####.##.#..##"), Ok(802));
     }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(super::part1(".#..#\n..x..").unwrap_err().to_string(), "line 2, column 3: unexpected character 'x'");
+    }
+
+    #[test]
+    fn map() {
+        let map = ".#..#\n.....\n#####\n....#\n...##".parse::<super::Map>().unwrap();
+
+        assert_eq!((map.width(), map.height(), map.asteroids().len()), (5, 5, 10));
+        assert!(map.has_asteroid_at((1, 0)) && !map.has_asteroid_at((0, 0)) && !map.has_asteroid_at((9, 9)));
+        assert!(!map.can_see((1, 0), (1, 4)) && map.can_see((1, 0), (4, 0)) && map.can_see((2, 2), (2, 2)));
+        assert_eq!(map.best_location(), Some(super::Station { x: 3, y: 4, visible: 8 }));
+        assert_eq!(map.vaporization_order((3, 4)).len(), 9);
+        assert_eq!(map.vaporization_order((3, 4)).last().map(|(_, rotation)| *rotation), Some(2));
+
+        let mut map = map;
+        assert!(map.blast_asteroid((1, 0)) && !map.blast_asteroid((1, 0)) && !map.blast_asteroid((9, 9)));
+        assert_eq!(super::part1("...").unwrap_err().to_string(), "the map has no asteroids");
+        assert_eq!(super::part2("#.#").unwrap_err().to_string(), "only 1 other asteroids to vaporize");
+    }
+
+    #[test]
+    fn render() {
+        let svg = super::render(".#..#\n.....\n#####\n....#\n...##").unwrap().to_string();
+
+        assert!(svg.contains("<title>Day 10: station at 3,4 sees 8 of 10 asteroids</title>"));
+        assert_eq!(svg.matches("<line ").count(), 8);
+        assert!(svg.contains(r#"<circle cx="3" cy="4" r="0.3" fill="red"/>"#));
+    }
+
+    /// Lines of sight as directions reduced by their greatest common divisor, so asteroids behind
+    /// each other share one.
+    fn sight_lines(asteroids: &[(i64, i64)], from: (i64, i64)) -> BTreeMap<(i64, i64), Vec<(i64, i64)>> {
+        let mut lines = BTreeMap::<_, Vec<_>>::new();
+
+        for &(x, y) in asteroids.iter().filter(|asteroid| **asteroid != from) {
+            let (dx, dy) = (x - from.0, y - from.1);
+            let divisor = super::gcd(dx.unsigned_abs() as usize, dy.unsigned_abs() as usize) as i64;
+
+            lines.entry((dx / divisor, dy / divisor)).or_default().push((x, y));
+        }
+
+        lines
+    }
+
+    fn reference(input: &str) -> (super::Station, super::Vaporization) {
+        let asteroids = input.lines().enumerate().flat_map(|(y, line)| {
+            line.char_indices().filter(|(_, c)| *c == '#').map(move |(x, _)| (x as i64, y as i64))
+        }).collect::<Vec<_>>();
+
+        let mut best = (asteroids[0], 0);
+
+        for asteroid in &asteroids {
+            let visible = sight_lines(&asteroids, *asteroid).len();
+
+            if visible > best.1 {
+                best = (*asteroid, visible);
+            }
+        }
+
+        let ((x, y), visible) = best;
+        let mut lines = sight_lines(&asteroids, (x, y)).into_iter().collect::<Vec<_>>();
+
+        // Clockwise from straight up, where y grows downwards
+        lines.sort_by(|((lhs_x, lhs_y), _), ((rhs_x, rhs_y), _)| {
+            let angle = |dx: i64, dy: i64| (dx as f64).atan2(-dy as f64).rem_euclid(2.0 * std::f64::consts::PI);
+            angle(*lhs_x, *lhs_y).partial_cmp(&angle(*rhs_x, *rhs_y)).unwrap()
+        });
+
+        for (_, line) in &mut lines {
+            line.sort_by_key(|(other_x, other_y)| (other_x - x).abs() + (other_y - y).abs());
+        }
+
+        let order = (0..).map(|rotation| lines.iter().filter_map(move |(_, line)| line.get(rotation).map(|asteroid| (*asteroid, rotation + 1))));
+        let (asteroid, rotations) = order.flatten().nth(199).unwrap();
+        let as_position = |(x, y): (i64, i64)| (x as usize, y as usize);
+
+        (
+            super::Station { x: x as usize, y: y as usize, visible },
+            super::Vaporization { station: as_position((x, y)), asteroid: as_position(asteroid), rotations },
+        )
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..20 {
+            let input = generate::asteroid_field(&mut Rng::new(seed), 24, 24, 250);
+            let (station, vaporization) = reference(&input);
+
+            assert_eq!(super::station(&input), Ok(station), "seed {}", seed);
+            assert_eq!(super::vaporization(&input), Ok(vaporization), "seed {}", seed);
+        }
+    }
 }
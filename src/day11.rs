@@ -1,202 +1,13 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
-use std::num::ParseIntError;
-use std::ops::{Index, IndexMut};
-use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
-
-struct Memory(Vec<i64>);
-
-impl Index<usize> for Memory {
-    type Output = i64;
-
-    fn index(&self, index: usize) -> &i64 {
-        if index < self.0.len() {
-            self.0.index(index)
-        } else {
-            &0
-        }
-    }
-}
-
-impl IndexMut<usize> for Memory {
-    fn index_mut(&mut self, index: usize) -> &mut i64 {
-        while self.0.len() <= index {
-            self.0.push(0);
-        }
-
-        self.0.index_mut(index)
-    }
-}
-
-#[derive(Clone, Copy)]
-enum Parameter {
-    Position(usize),
-    Immediate(i64),
-    Relative(isize),
-}
-
-impl Parameter {
-    fn new(mode: i64, value: i64) -> Parameter {
-        match mode {
-            0 => Parameter::Position(value as usize),
-            1 => Parameter::Immediate(value),
-            2 => Parameter::Relative(value as isize),
-            _ => panic!("Invalid parameter mode {}", mode),
-        }
-    }
-
-    fn read(memory: &Memory, eip: usize, offset: usize) -> Parameter {
-        let mode = (memory[eip] / 10i64.pow(1 + (offset as u32))) % 10;
-        let value = memory[eip + offset];
-
-        Parameter::new(mode, value)
-    }
-
-    fn load(self, memory: &Memory, rbo: usize) -> i64 {
-        match self {
-            Parameter::Position(pos) => memory[pos],
-            Parameter::Immediate(value) => value,
-            Parameter::Relative(offset) => memory[((rbo as isize) + offset) as usize],
-        }
-    }
-
-    fn store(self, value: i64, memory: &mut Memory, rbo: usize) {
-        match self {
-            Parameter::Position(pos) => memory[pos] = value,
-            Parameter::Immediate(_) => panic!("Cannot store to an immediate mode parameter"),
-            Parameter::Relative(offset) => memory[((rbo as isize) + offset) as usize] = value,
-        }
-    }
-}
-
-struct Process {
-    memory: Memory,
-    eip: usize,
-    rbo: usize,
-    input_buffer: VecDeque<i64>,
-    output_buffer: VecDeque<i64>,
-}
-
-#[derive(PartialEq)]
-enum ProcessRunResult {
-    Complete,
-    WouldBlock,
-}
-
-impl Process {
-    fn feed(&mut self, value: i64) {
-        self.input_buffer.push_back(value);
-    }
-
-    fn read(&mut self) -> Option<i64> {
-        self.output_buffer.pop_front()
-    }
-
-    fn run(&mut self) -> ProcessRunResult {
-        loop {
-            match self.memory[self.eip] % 100 {
-                1 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(lhs.load(&self.memory, self.rbo) + rhs.load(&self.memory, self.rbo), &mut self.memory, self.rbo);
-                    self.eip += 4;
-                }
-                2 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(lhs.load(&self.memory, self.rbo) * rhs.load(&self.memory, self.rbo), &mut self.memory, self.rbo);
-                    self.eip += 4;
-                }
-                3 => {
-                    let input = match self.input_buffer.pop_front() {
-                        None => return ProcessRunResult::WouldBlock,
-                        Some(value) => value,
-                    };
-
-                    let out = Parameter::read(&self.memory, self.eip, 1);
-                    out.store(input, &mut self.memory, self.rbo);
-                    self.eip += 2;
-                }
-                4 => {
-                    let src = Parameter::read(&self.memory, self.eip, 1);
-                    self.output_buffer.push_back(src.load(&self.memory, self.rbo));
-                    self.eip += 2;
-                }
-                5 => {
-                    let test = Parameter::read(&self.memory, self.eip, 1);
-                    let jump = Parameter::read(&self.memory, self.eip, 2);
-
-                    if test.load(&self.memory, self.rbo) != 0 {
-                        self.eip = jump.load(&self.memory, self.rbo) as usize;
-                    } else {
-                        self.eip += 3;
-                    }
-                }
-                6 => {
-                    let test = Parameter::read(&self.memory, self.eip, 1);
-                    let jump = Parameter::read(&self.memory, self.eip, 2);
-
-                    if test.load(&self.memory, self.rbo) == 0 {
-                        self.eip = jump.load(&self.memory, self.rbo) as usize;
-                    } else {
-                        self.eip += 3;
-                    }
-                }
-                7 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(if lhs.load(&self.memory, self.rbo) < rhs.load(&self.memory, self.rbo) { 1 } else { 0 }, &mut self.memory, self.rbo);
-                    self.eip += 4;
-                }
-                8 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(if lhs.load(&self.memory, self.rbo) == rhs.load(&self.memory, self.rbo) { 1 } else { 0 }, &mut self.memory, self.rbo);
-                    self.eip += 4;
-                }
-                9 => {
-                    let val = Parameter::read(&self.memory, self.eip, 1);
-                    self.rbo = ((self.rbo as i64) + val.load(&self.memory, self.rbo)) as usize;
-                    self.eip += 2;
-                }
-                99 => {
-                    return ProcessRunResult::Complete;
-                }
-                op => {
-                    panic!("Unknown op code: {}", op);
-                }
-            }
-        }
-    }
-}
-
-struct Program(Vec<i64>);
-
-impl FromStr for Program {
-    type Err = ParseIntError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.split(',').map(|v| v.parse::<i64>()).collect::<Result<Vec<_>, _>>().map(|r| Program(r))
-    }
-}
-
-impl Program {
-    fn spawn(&self) -> Process {
-        Process {
-            memory: Memory(self.0.clone()),
-            eip: 0,
-            rbo: 0,
-            input_buffer: VecDeque::new(),
-            output_buffer: VecDeque::new(),
-        }
-    }
-}
+use crate::error::SolveError;
+use crate::intcode::{Process, Program, StopReason};
+use crate::json::Value;
+use crate::json_object;
+use crate::runner::Solution;
+use crate::svg::Svg;
 
 #[derive(Clone, Copy, PartialEq)]
 enum Direction {
@@ -212,28 +23,33 @@ enum Turn {
     Right,
 }
 
-impl From<i64> for Turn {
-    fn from(v: i64) -> Turn {
+impl TryFrom<i64> for Turn {
+    type Error = SolveError;
+
+    fn try_from(v: i64) -> Result<Turn, SolveError> {
         match v {
-            0 => Turn::Left,
-            1 => Turn::Right,
-            _ => panic!("Invalid turn: {}", v),
+            0 => Ok(Turn::Left),
+            1 => Ok(Turn::Right),
+            _ => Err(SolveError::Unsolvable(format!("the robot turns by {}", v))),
         }
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// The paint of a panel. Every panel starts out black.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Color {
     Black,
     White,
 }
 
-impl From<i64> for Color {
-    fn from(v: i64) -> Color {
+impl TryFrom<i64> for Color {
+    type Error = SolveError;
+
+    fn try_from(v: i64) -> Result<Color, SolveError> {
         match v {
-            0 => Color::Black,
-            1 => Color::White,
-            _ => panic!("Invalid color: {}", v),
+            0 => Ok(Color::Black),
+            1 => Ok(Color::White),
+            _ => Err(SolveError::Unsolvable(format!("the robot paints in color {}", v))),
         }
     }
 }
@@ -271,153 +87,204 @@ impl HullPaintingRobot {
     }
 }
 
+/// The side of the ship, addressed by `(x, y)` from where the robot starts with `y` growing
+/// downwards, and the path the robot took over it.
+#[derive(Default)]
 pub struct Hull {
     panels: HashMap<(isize, isize), Color>,
+    /// Every position the robot has been at, in order.
+    trail: Vec<(isize, isize)>,
 }
 
 impl Hull {
-    fn new() -> Hull {
-        let mut panels = HashMap::new();
-        panels.insert((0, 0), Color::White);
-        Hull { panels }
+    pub fn new() -> Hull {
+        Hull::default()
     }
 
-    fn paint(&mut self, position: (isize, isize), paint: Color) {
+    pub fn paint(&mut self, position: (isize, isize), paint: Color) {
         self.panels.insert(position, paint);
     }
 
-    fn look(&mut self, position: (isize, isize)) -> Color {
+    pub fn look(&self, position: (isize, isize)) -> Color {
         *self.panels.get(&position).unwrap_or(&Color::Black)
     }
-}
 
-impl fmt::Display for Hull {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "")?;
+    /// Every panel that has been painted at least once, with its current color.
+    pub fn panels(&self) -> &HashMap<(isize, isize), Color> {
+        &self.panels
+    }
 
+    /// Every position the robot has been at, in order, starting where it was put down.
+    pub fn trail(&self) -> &[(isize, isize)] {
+        &self.trail
+    }
+
+    /// Rows of the painted area, `white` for white panels and `black` for the rest. Panics when
+    /// nothing has been painted.
+    pub fn rows(&self, white: char, black: char) -> Vec<String> {
         let lo_x = self.panels.keys().map(|(x, _)| *x).min().unwrap();
         let hi_x = self.panels.keys().map(|(x, _)| *x).max().unwrap();
         let lo_y = self.panels.keys().map(|(_, y)| *y).min().unwrap();
         let hi_y = self.panels.keys().map(|(_, y)| *y).max().unwrap();
 
-        for y in lo_y ..= hi_y {
-            for x in lo_x ..= hi_x {
-                write!(f, "{}", if self.panels.get(&(x, y)) != Some(&Color::White) { "█" } else { " " })?;
-            }
+        (lo_y ..= hi_y).map(|y| {
+            (lo_x ..= hi_x).map(|x| if self.panels.get(&(x, y)) != Some(&Color::White) { black } else { white }).collect()
+        }).collect()
+    }
+}
+
+impl fmt::Display for Hull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f)?;
 
-            writeln!(f, "")?;
+        for row in self.rows(' ', '█') {
+            writeln!(f, "{}", row)?;
         }
 
         Ok(())
     }
 }
 
-#[aoc(day11, part1)]
-pub fn part1(input: &str) -> Result<usize, ParseIntError> {
-    let program = input.parse::<Program>()?;
-    let mut process = program.spawn();
-    let mut panels = HashMap::<(isize, isize), Color>::new();
+impl Solution for Hull {
+    fn report(&self) -> Option<Value> {
+        Some(json_object! {
+            "painted" => self.panels.len(),
+            "white" => self.panels.values().filter(|color| **color == Color::White).count(),
+            "moves" => self.trail.len().saturating_sub(1),
+            "rows" => self.rows('#', '.'),
+        })
+    }
+}
+
+/// Runs the robot's program over `hull`, starting at `(0, 0)` facing up, until it halts. Fails when
+/// the program stops for any other reason or asks for a color or turn that does not exist.
+pub fn paint(program: &Program, hull: Hull) -> Result<Hull, SolveError> {
+    paint_with(&mut program.spawn(), hull)
+}
+
+/// Like `paint`, but drives a process the caller set up, for example to record it or to replay a
+/// recording. Inputs already queued on the process are used before the camera is looked at.
+pub fn paint_with(process: &mut Process, mut hull: Hull) -> Result<Hull, SolveError> {
     let mut robot = HullPaintingRobot::new();
+    let mut paint = Option::<Color>::None;
+
+    hull.trail.push(robot.position);
 
     loop {
-        match process.run() {
-            ProcessRunResult::Complete => break,
-            ProcessRunResult::WouldBlock => {},
+        let reason = process.run();
+
+        while let Some(value) = process.read() {
+            match paint.take() {
+                None => paint = Some(Color::try_from(value)?),
+                Some(color) => {
+                    hull.paint(robot.position, color);
+                    robot.step(Turn::try_from(value)?);
+                    hull.trail.push(robot.position);
+                }
+            }
         }
 
-        while let Some(paint) = process.read() {
-            panels.insert(robot.position, paint.into());
-            robot.step(process.read().unwrap().into());
+        match reason {
+            StopReason::Halted => return Ok(hull),
+            StopReason::WaitingForInput => process.feed(hull.look(robot.position).into()),
+            reason => return Err(SolveError::Unsolvable(format!("the robot stopped at {}: {}", process.eip(), reason))),
         }
-
-        process.feed((*panels.get(&robot.position).unwrap_or(&Color::Black)).into());
     }
+}
+
+pub fn part1(input: &str) -> Result<usize, SolveError> {
+    let program = input.parse::<Program>()?;
+    let hull = paint(&program, Hull::new())?;
 
-    Ok(panels.len())
+    Ok(hull.panels.len())
 }
 
-#[aoc(day11, part2)]
-pub fn part2(input: &str) -> Result<Hull, ParseIntError> {
+pub fn part2(input: &str) -> Result<Hull, SolveError> {
     let program = input.parse::<Program>()?;
-    let mut process = program.spawn();
-    let mut robot = HullPaintingRobot::new();
     let mut hull = Hull::new();
 
-    loop {
-        match process.run() {
-            ProcessRunResult::Complete => break,
-            ProcessRunResult::WouldBlock => {},
-        }
+    hull.paint((0, 0), Color::White);
 
-        while let Some(paint) = process.read() {
-            hull.paint(robot.position, paint.into());
-            robot.step(process.read().unwrap().into());
-        }
+    paint(&program, hull)
+}
+
+/// Draws the registration identifier painted in part 2, with the path the robot took on top.
+pub fn render(input: &str) -> Result<Svg, SolveError> {
+    let hull = part2(input)?;
+    let mut panels = hull.panels.iter().map(|(position, color)| (*position, *color)).collect::<Vec<_>>();
+
+    panels.sort_by_key(|(position, _)| (position.1, position.0));
+
+    let cells = panels.iter().map(|(position, _)| *position).chain(hull.trail.iter().copied());
+    let mut svg = Svg::around(cells.map(|(x, y)| (x as f64, y as f64)), 0.05);
+    let center = |(x, y): (isize, isize)| (x as f64, y as f64);
 
-        process.feed(hull.look(robot.position).into());
+    svg.title(&format!("Day 11: {} panels painted, {} robot moves", panels.len(), hull.trail.len() - 1));
+    svg.background("black");
+
+    for ((x, y), color) in &panels {
+        let fill = if *color == Color::White { "white" } else { "dimgray" };
+
+        svg.rect((*x as f64 - 0.5, *y as f64 - 0.5), (1.0, 1.0), &format!(r#"fill="{}""#, fill));
     }
 
-    Ok(hull)
+    svg.polyline(&hull.trail.iter().copied().map(center).collect::<Vec<_>>(), r#"stroke="orange" stroke-width="0.1" stroke-opacity="0.7""#);
+    svg.circle(center(hull.trail[0]), 0.3, r#"fill="green""#);
+    svg.circle(center(hull.trail[hull.trail.len() - 1]), 0.3, r#"fill="red""#);
+
+    Ok(svg)
 }
 
 #[cfg(test)]
 mod test {
-    use super::Program;
+    use super::{Color, Hull};
+    use crate::intcode::replay::Replay;
+    use crate::intcode::Program;
 
-    fn run(source: &str, input: Vec<i64>) -> Vec<i64> {
-        let program = source.parse::<Program>().unwrap();
+    #[test]
+    fn hull() {
+        // Paints white and turns left twice, ignoring the camera
+        let hull = super::paint(&"104,1,104,0,104,1,104,0,99".parse().unwrap(), Hull::new()).unwrap();
 
-        let mut process = program.spawn();
-        let mut input = input.iter();
-        let mut output = Vec::new();
+        assert_eq!(hull.panels().len(), 2);
+        assert_eq!(hull.look((-1, 0)), Color::White);
+        assert_eq!(hull.look((5, 5)), Color::Black);
+        assert_eq!(hull.trail(), [(0, 0), (-1, 0), (-1, 1)]);
+        assert_eq!(hull.rows('#', '.'), ["##"]);
 
-        loop {
-            match process.run() {
-                super::ProcessRunResult::Complete => break,
-                super::ProcessRunResult::WouldBlock => process.input_buffer.push_back(*input.next().unwrap()),
-            }
-        }
+        let err = super::paint(&"104,1,104,2,99".parse().unwrap(), Hull::new()).err().unwrap();
+        assert_eq!(err.to_string(), "the robot turns by 2");
 
-        while let Some(foo) = process.read() {
-            output.push(foo);
-        }
-
-        output
+        let err = super::paint(&"104,1,104,0,22".parse().unwrap(), Hull::new()).err().unwrap();
+        assert_eq!(err.to_string(), "the robot stopped at 4: unknown opcode 22");
     }
 
     #[test]
-    fn day5_part1() {
-        assert_eq!(run("1002,4,3,4,33", vec![]), vec![]);
-        assert_eq!(run("1101,100,-1,4,0", vec![]), vec![]);
-        assert_eq!(run("1002,6,3,6,4,0,33", vec![]), vec![1002]);
-        assert_eq!(run("3,5,4,5,99,0", vec![-16]), vec![-16]);
-    }
-
-    #[test]
-    fn day5_part2() {
-        assert_eq!(run("3,9,8,9,10,9,4,9,99,-1,8", vec![7]), vec![0]);
-        assert_eq!(run("3,9,8,9,10,9,4,9,99,-1,8", vec![8]), vec![1]);
-
-        assert_eq!(run("3,9,7,9,10,9,4,9,99,-1,8", vec![7]), vec![1]);
-        assert_eq!(run("3,9,7,9,10,9,4,9,99,-1,8", vec![8]), vec![0]);
-
-        assert_eq!(run("3,3,1108,-1,8,3,4,3,99", vec![7]), vec![0]);
-        assert_eq!(run("3,3,1108,-1,8,3,4,3,99", vec![8]), vec![1]);
+    fn replay() {
+        // Ten times paints black panels white and turns right, or white ones black and turns left
+        let program = "3,100,1008,100,0,101,4,101,4,101,1001,102,1,102,1007,102,10,103,1005,103,0,99".parse::<Program>().unwrap();
+        let mut process = program.spawn();
 
-        assert_eq!(run("3,3,1107,-1,8,3,4,3,99", vec![7]), vec![1]);
-        assert_eq!(run("3,3,1107,-1,8,3,4,3,99", vec![8]), vec![0]);
+        process.record();
 
-        assert_eq!(run("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", vec![0]), vec![0]);
-        assert_eq!(run("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", vec![2]), vec![1]);
+        let hull = super::paint_with(&mut process, Hull::new()).unwrap();
+        let journal = process.journal().unwrap().to_string();
+        let mut replay = Replay::new(&program, journal.parse().unwrap());
+        let replayed = super::paint_with(replay.process_mut(), Hull::new()).unwrap();
 
-        assert_eq!(run("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", vec![0]), vec![0]);
-        assert_eq!(run("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", vec![2]), vec![1]);
+        assert_eq!(replay.verify(), Ok(30));
+        assert_eq!(replayed.panels(), hull.panels());
+        assert_eq!(replayed.trail(), hull.trail());
+        assert_eq!(hull.trail().len(), 11);
     }
 
     #[test]
-    fn day9_part1() {
-        assert_eq!(run("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99", vec![]), vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]);
-        assert_eq!(run("1102,34915192,34915192,7,4,7,99,0", vec![]), vec![34915192 * 34915192]);
-        assert_eq!(run("104,1125899906842624,99", vec![]), vec![1125899906842624]);
+    fn render() {
+        // Paints white and turns left twice, ignoring the camera
+        let svg = super::render("104,1,104,0,104,1,104,0,99").unwrap().to_string();
+
+        assert!(svg.contains("<title>Day 11: 2 panels painted, 2 robot moves</title>"));
+        assert!(svg.contains(r#"points="0,0 -1,0 -1,1""#));
+        assert_eq!(svg.matches(r#"fill="white""#).count(), 2);
     }
 }
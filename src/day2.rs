@@ -1,15 +1,14 @@
-use std::num::ParseIntError;
 use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
+use crate::error::{number, parse_fields, ParseError};
 
 struct Program(Vec<u64>);
 
 impl FromStr for Program {
-    type Err = ParseIntError;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.split(',').map(|v| v.parse::<u64>()).collect::<Result<Vec<_>, _>>().map(|r| Program(r))
+        parse_fields(s, ',', number).map(Program)
     }
 }
 
@@ -50,14 +49,12 @@ impl Program {
     }
 }
 
-#[aoc(day2, part1)]
-pub fn part1(input: &str) -> Result<u64, ParseIntError> {
+pub fn part1(input: &str) -> Result<u64, ParseError> {
     let program = input.parse::<Program>()?;
     Ok(program.run(12, 2))
 }
 
-#[aoc(day2, part2)]
-pub fn part2(input: &str) -> Result<u64, ParseIntError> {
+pub fn part2(input: &str) -> Result<u64, ParseError> {
     let program = input.parse::<Program>()?;
 
     for noun in 0..=99 {
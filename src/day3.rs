@@ -1,24 +1,32 @@
+use std::fmt;
 use std::iter::IntoIterator;
-use std::num::ParseIntError;
 use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
-
-#[derive(Clone, Copy, PartialEq)]
-struct Point {
-    x: isize,
-    y: isize,
+use crate::error::{number, parse_fields, parse_lines, ErrorKind, ParseError};
+use crate::json::Value;
+use crate::json_object;
+use crate::runner::Solution;
+use crate::svg::Svg;
+
+/// A place on the grid, with `y` growing upwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: isize,
+    pub y: isize,
 }
 
 impl Point {
-    const ZERO: Point = Point { x: 0, y: 0 };
+    /// The central port, where both wires start.
+    pub const ZERO: Point = Point { x: 0, y: 0 };
 
     pub const fn manhattan_distance(self) -> usize {
         (self.x.abs() + self.y.abs()) as usize
     }
 }
 
-enum Instruction {
+/// One straight stretch of wire, like `U7`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
     Up(isize),
     Down(isize),
     Left(isize),
@@ -26,32 +34,73 @@ enum Instruction {
 }
 
 impl FromStr for Instruction {
-    type Err = ParseIntError;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.chars().next().unwrap() {
-            'U' => s[1..].parse().map(Instruction::Up),
-            'D' => s[1..].parse().map(Instruction::Down),
-            'L' => s[1..].parse().map(Instruction::Left),
-            'R' => s[1..].parse().map(Instruction::Right),
-            _ => unreachable!(),
+        let mut chars = s.chars();
+        let direction = chars.next().ok_or(ParseError::new(ErrorKind::Missing("direction")))?;
+        let distance = number(chars.as_str()).map_err(|err| err.at(1, 2))?;
+
+        if distance < 0 {
+            return Err(ParseError::new(ErrorKind::Invalid(format!("negative distance {}", distance))).at(1, 2));
+        }
+
+        match direction {
+            'U' => Ok(Instruction::Up(distance)),
+            'D' => Ok(Instruction::Down(distance)),
+            'L' => Ok(Instruction::Left(distance)),
+            'R' => Ok(Instruction::Right(distance)),
+            c => Err(ParseError::new(ErrorKind::UnexpectedChar(c))),
         }
     }
 }
 
-struct InstructionSet(Vec<Instruction>);
+/// One wire as the comma separated instructions that lay it out from the central port.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstructionSet(Vec<Instruction>);
 
 impl InstructionSet {
+    pub fn new(instructions: Vec<Instruction>) -> InstructionSet {
+        InstructionSet(instructions)
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.0
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The corners of the wire, starting at the central port.
+    pub fn vertices(&self) -> Vec<Point> {
+        let mut point = Point::ZERO;
+        let mut result = vec![point];
+
+        for instruction in &self.0 {
+            match *instruction {
+                Instruction::Up(v) => point.y += v,
+                Instruction::Down(v) => point.y -= v,
+                Instruction::Left(v) => point.x -= v,
+                Instruction::Right(v) => point.x += v,
+            }
+
+            result.push(point);
+        }
+
+        result
+    }
 }
 
 impl FromStr for InstructionSet {
-    type Err = ParseIntError;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.split(',').map(|i| Instruction::from_str(i)).collect::<Result<Vec<_>, _>>().map(InstructionSet)
+        parse_fields(s, ',', Instruction::from_str).map(InstructionSet)
     }
 }
 
@@ -64,37 +113,45 @@ impl IntoIterator for InstructionSet {
     }
 }
 
-struct ProgramInput {
-    first: InstructionSet,
-    second: InstructionSet,
+/// The puzzle input: two wires, one per line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wires {
+    pub first: InstructionSet,
+    pub second: InstructionSet,
 }
 
-impl FromStr for ProgramInput {
-    type Err = ParseIntError;
+impl FromStr for Wires {
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iter = s.split_whitespace();
+        let mut wires = parse_lines(s, InstructionSet::from_str)?.into_iter();
+        let first = wires.next().ok_or(ParseError::new(ErrorKind::Missing("first wire")))?;
+        let second = wires.next().ok_or(ParseError::new(ErrorKind::Missing("second wire")).at(2, 1))?;
 
-        Ok(ProgramInput {
-            first: iter.next().unwrap().parse()?,
-            second: iter.next().unwrap().parse()?,
-        })
+        if wires.next().is_some() {
+            return Err(ParseError::new(ErrorKind::Invalid("expected only two wires".to_string())).at(3, 1));
+        }
+
+        Ok(Wires { first, second })
     }
 }
 
-#[derive(Clone, Copy)]
-enum PathSegmentDirection {
+/// Whether a segment is walked towards growing or shrinking coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegmentDirection {
     Ascending,
     Descending,
 }
 
-#[derive(Clone, Copy)]
-enum PathSegment {
+/// A straight piece of wire, stored by its bounds along with the direction it was laid in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegment {
     Horizontal { y: isize, x_lo: isize, x_hi: isize, dir: PathSegmentDirection },
     Vertical { x: isize, y_lo: isize, y_hi: isize, dir: PathSegmentDirection },
 }
 
 impl PathSegment {
+    /// Steps from one end to the other.
     pub fn len(self) -> usize {
         match self {
             PathSegment::Horizontal { x_lo, x_hi, .. } => (x_hi - x_lo) as usize,
@@ -102,6 +159,12 @@ impl PathSegment {
         }
     }
 
+    /// Whether the segment came from an instruction like `U0` and covers a single point.
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Steps from the start of the segment to `point`, which has to lie on it.
     pub fn offset_of(self, point: Point) -> usize {
         match self {
             PathSegment::Horizontal { dir: PathSegmentDirection::Ascending, x_lo, .. } => (point.x - x_lo) as usize,
@@ -111,6 +174,7 @@ impl PathSegment {
         }
     }
 
+    /// Where a horizontal and a vertical segment cross. Parallel segments never do.
     pub fn intersection(self, other: PathSegment) -> Option<Point> {
         match (self, other) {
             (PathSegment::Horizontal { y, x_lo, x_hi, .. }, PathSegment::Vertical { y_lo, y_hi, x, .. }) => {
@@ -124,12 +188,26 @@ impl PathSegment {
     }
 }
 
-struct Path(Vec<PathSegment>);
+/// A wire as segments in the order it is laid out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path(Vec<PathSegment>);
 
 impl Path {
-    fn iter(&self) -> std::slice::Iter<PathSegment> {
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, PathSegment> {
         self.0.iter()
     }
+
+    /// Every point where the two wires cross, except the central port, in no particular order.
+    pub fn crossings(&self, other: &Path) -> Vec<Point> {
+        self.iter()
+            .flat_map(|segment| other.iter().filter_map(move |other| segment.intersection(*other)))
+            .filter(|point| *point != Point::ZERO)
+            .collect()
+    }
 }
 
 impl From<InstructionSet> for Path {
@@ -163,50 +241,67 @@ impl From<InstructionSet> for Path {
     }
 }
 
-#[aoc(day3, part1)]
-pub fn part1(input: &str) -> Result<u64, ParseIntError> {
-    let input: ProgramInput = input.parse()?;
+/// The crossing that wins a part, with the distance that part measures.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+    pub x: isize,
+    pub y: isize,
+    pub distance: u64,
+}
 
-    let first_path: Path = input.first.into();
-    let second_path: Path = input.second.into();
+impl Crossing {
+    const NONE: Crossing = Crossing { x: 0, y: 0, distance: usize::MAX as u64 };
 
-    let mut min_distance = usize::max_value();
+    fn closer(self, point: Point, distance: usize) -> Crossing {
+        if (distance as u64) < self.distance { Crossing { x: point.x, y: point.y, distance: distance as u64 } } else { self }
+    }
+}
 
-    for segment in first_path.iter() {
-        for other in second_path.iter() {
-            if let Some(intersection) = segment.intersection(*other) {
-                if intersection == Point::ZERO { continue; }
-                let distance = intersection.manhattan_distance();
-                min_distance = std::cmp::min(min_distance, distance);
-            }
-        }
+impl fmt::Display for Crossing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.distance)
     }
+}
 
-    Ok(min_distance as u64)
+impl Solution for Crossing {
+    fn report(&self) -> Option<Value> {
+        Some(json_object! { "x" => self.x as i64, "y" => self.y as i64, "distance" => self.distance })
+    }
 }
 
-#[aoc(day3, part2)]
-pub fn part2(input: &str) -> Result<u64, ParseIntError> {
-    let input: ProgramInput = input.parse()?;
+/// The crossing nearest to the central port.
+pub fn closest_crossing(input: &str) -> Result<Crossing, ParseError> {
+    let input: Wires = input.parse()?;
 
     let first_path: Path = input.first.into();
     let second_path: Path = input.second.into();
 
-    let mut min_distance = usize::max_value();
+    Ok(first_path.crossings(&second_path).into_iter().fold(Crossing::NONE, |closest, point| closest.closer(point, point.manhattan_distance())))
+}
+
+pub fn part1(input: &str) -> Result<u64, ParseError> {
+    closest_crossing(input).map(|crossing| crossing.distance)
+}
+
+/// The crossing both wires reach in the fewest combined steps.
+pub fn quickest_crossing(input: &str) -> Result<Crossing, ParseError> {
+    let input: Wires = input.parse()?;
+
+    let first_path: Path = input.first.into();
+    let second_path: Path = input.second.into();
+
+    let mut quickest = Crossing::NONE;
 
     let mut first_len = 0;
     for segment in first_path.iter() {
         let mut second_len = 0;
 
         for other in second_path.iter() {
-            if let Some(intersection) = segment.intersection(*other) {
-                if intersection == Point::ZERO { continue; }
-
+            if let Some(intersection) = segment.intersection(*other).filter(|point| *point != Point::ZERO) {
                 let first_offset = segment.offset_of(intersection);
                 let second_offset = other.offset_of(intersection);
 
-                let distance = first_len + first_offset + second_len + second_offset;
-                min_distance = std::cmp::min(min_distance, distance);
+                quickest = quickest.closer(intersection, first_len + first_offset + second_len + second_offset);
             }
 
             second_len += other.len();
@@ -215,11 +310,52 @@ pub fn part2(input: &str) -> Result<u64, ParseIntError> {
         first_len += segment.len();
     }
 
-    Ok(min_distance as u64)
+    Ok(quickest)
+}
+
+pub fn part2(input: &str) -> Result<u64, ParseError> {
+    quickest_crossing(input).map(|crossing| crossing.distance)
+}
+
+/// Draws both wires with every crossing marked, the one closest to the central port in red.
+pub fn render(input: &str) -> Result<Svg, ParseError> {
+    let input: Wires = input.parse()?;
+
+    // Up is positive in the puzzle but points down in SVG
+    let flip = |point: &Point| (point.x as f64, -point.y as f64);
+    let first = input.first.vertices().iter().map(flip).collect::<Vec<_>>();
+    let second = input.second.vertices().iter().map(flip).collect::<Vec<_>>();
+
+    let first_path: Path = input.first.into();
+    let second_path: Path = input.second.into();
+    let mut crossings = first_path.crossings(&second_path);
+
+    crossings.sort_by_key(|point| point.manhattan_distance());
+
+    let mut svg = Svg::around(first.iter().chain(&second).copied(), 0.02);
+    let width = svg.extent() / 500.0;
+
+    svg.title(&format!("Day 3: {} crossings", crossings.len()));
+    svg.polyline(&first, &format!(r#"stroke="steelblue" stroke-width="{:.3}""#, width));
+    svg.polyline(&second, &format!(r#"stroke="darkorange" stroke-width="{:.3}""#, width));
+
+    for (i, point) in crossings.iter().enumerate() {
+        let color = if i == 0 { "red" } else { "black" };
+
+        svg.circle(flip(point), width * 4.0, &format!(r#"fill="{}""#, color));
+    }
+
+    svg.circle((0.0, 0.0), width * 6.0, r#"fill="green""#);
+
+    Ok(svg)
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
+    use crate::generate::{self, Rng};
+
     #[test]
     fn part_1_should_give_6() {
         assert_eq!(super::part1("R8,U5,L5,D3\nU7,R6,D4,L4"), Ok(6));
@@ -239,4 +375,81 @@ mod test {
     fn part_2_should_give_410() {
         assert_eq!(super::part2("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7"), Ok(410));
     }
+
+    #[test]
+    fn parse_errors() {
+        use crate::error::ErrorKind;
+
+        let err = super::part1("R8,U5\nU7,X6").unwrap_err();
+
+        assert_eq!((err.line, err.column, err.kind), (2, 4, ErrorKind::UnexpectedChar('X')));
+        assert_eq!(super::part1("R8,U5,L").unwrap_err().to_string(), "line 1, column 8: cannot parse integer from empty string");
+        assert_eq!(super::part1("R8").unwrap_err().kind, ErrorKind::Missing("second wire"));
+        assert_eq!(super::part1("R8,U5\nU7,D-5").unwrap_err().to_string(), "line 2, column 5: negative distance -5");
+    }
+
+    #[test]
+    fn render() {
+        let svg = super::render("R8,U5,L5,D3\nU7,R6,D4,L4").unwrap().to_string();
+
+        assert!(svg.contains("<title>Day 3: 2 crossings</title>"));
+        assert!(svg.contains(r#"points="0,0 8,0 8,-5 3,-5 3,-2""#));
+        assert!(svg.contains(r#"<circle cx="3" cy="-3" "#));
+        assert!(svg.contains(r#"fill="red"/>"#));
+    }
+
+    #[test]
+    fn reports() {
+        use crate::runner::Solution;
+
+        let crossing = super::quickest_crossing("R8,U5,L5,D3\nU7,R6,D4,L4").unwrap();
+
+        assert_eq!(crossing, super::Crossing { x: 6, y: 5, distance: 30 });
+        assert_eq!(crossing.report().unwrap().to_string(), r#"{"x":6,"y":5,"distance":30}"#);
+    }
+
+    /// Walks both wires one step at a time, remembering when each wire first got to every point.
+    fn crossings(input: &str) -> Vec<((i64, i64), u64, u64)> {
+        let walk = |line: &str| {
+            let mut position = (0i64, 0i64);
+            let mut walked = 0u64;
+            let mut steps = HashMap::new();
+
+            for instruction in line.split(',') {
+                let (dx, dy) = match &instruction[..1] {
+                    "U" => (0, 1),
+                    "D" => (0, -1),
+                    "L" => (-1, 0),
+                    _ => (1, 0),
+                };
+
+                for _ in 0..instruction[1..].parse::<u64>().unwrap() {
+                    position = (position.0 + dx, position.1 + dy);
+                    walked += 1;
+                    steps.entry(position).or_insert(walked);
+                }
+            }
+
+            steps
+        };
+
+        let mut lines = input.lines();
+        let first = walk(lines.next().unwrap());
+        let second = walk(lines.next().unwrap());
+
+        second.iter().filter_map(|(point, steps)| first.get(point).map(|first_steps| (*point, *first_steps, *steps))).collect()
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..50 {
+            let input = generate::wires(&mut Rng::new(seed), 30);
+            let crossings = crossings(&input);
+            let closest = crossings.iter().map(|((x, y), _, _)| (x.abs() + y.abs()) as u64).min().unwrap_or(usize::MAX as u64);
+            let quickest = crossings.iter().map(|(_, first, second)| first + second).min().unwrap_or(usize::MAX as u64);
+
+            assert_eq!(super::part1(&input), Ok(closest), "seed {}", seed);
+            assert_eq!(super::part2(&input), Ok(quickest), "seed {}", seed);
+        }
+    }
 }
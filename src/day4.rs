@@ -1,7 +1,6 @@
-use std::num::ParseIntError;
 use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
+use crate::error::{column_of, number, ErrorKind, ParseError};
 
 struct Bounds {
     lo: u64,
@@ -9,12 +8,12 @@ struct Bounds {
 }
 
 impl FromStr for Bounds {
-    type Err = ParseIntError;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split('-').map(|n| n.parse::<u64>());
+        let (lo, hi) = s.split_once('-').ok_or_else(|| ParseError::new(ErrorKind::Missing("`-`")).at(1, s.chars().count() + 1))?;
 
-        Ok(Bounds { lo: parts.next().unwrap()?, hi: parts.next().unwrap()? })
+        Ok(Bounds { lo: number(lo)?, hi: number(hi).map_err(|err| err.at(1, column_of(s, hi)))? })
     }
 }
 
@@ -31,7 +30,7 @@ fn number_to_digits(num: u64) -> Vec<u64> {
 
     while num > 9 {
         digits.push(num % 10);
-        num = num / 10;
+        num /= 10;
     }
 
     digits.push(num);
@@ -73,15 +72,13 @@ fn password_is_valid_v2(digits: Vec<u64>) -> bool {
     has_exactly_two_adjecent(digits) && never_decreases
 }
 
-#[aoc(day4, part1)]
-pub fn part1(input: &str) -> Result<u64, ParseIntError> {
+pub fn part1(input: &str) -> Result<u64, ParseError> {
     let bounds: Bounds = input.parse()?;
 
     Ok(bounds.count_valid_passwords(&password_is_valid_v1) as u64)
 }
 
-#[aoc(day4, part2)]
-pub fn part2(input: &str) -> Result<u64, ParseIntError> {
+pub fn part2(input: &str) -> Result<u64, ParseError> {
     let bounds: Bounds = input.parse()?;
 
     Ok(bounds.count_valid_passwords(&password_is_valid_v2) as u64)
@@ -89,6 +86,8 @@ pub fn part2(input: &str) -> Result<u64, ParseIntError> {
 
 #[cfg(test)]
 mod test {
+    use crate::generate::{self, Rng};
+
     #[test]
     fn number_to_digits() {
         assert_eq!(super::number_to_digits(123456), vec![1, 2, 3, 4, 5, 6]);
@@ -124,4 +123,34 @@ mod test {
     fn part_2_should_give_22() {
         assert_eq!(super::part2("111122-111322"), Ok(22));
     }
+
+    /// Checks the rules on the decimal digits as text.
+    fn valid(password: u64, exact_pair: bool) -> bool {
+        let digits = password.to_string().into_bytes();
+        let mut runs = Vec::new();
+
+        for digit in &digits {
+            match runs.last_mut() {
+                Some((last, length)) if last == digit => *length += 1,
+                _ => runs.push((*digit, 1)),
+            }
+        }
+
+        let mut sorted = digits.clone();
+
+        sorted.sort_unstable();
+        sorted == digits && runs.iter().any(|(_, length)| if exact_pair { *length == 2 } else { *length >= 2 })
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..50 {
+            let input = generate::password_range(&mut Rng::new(seed), 20_000);
+            let (lo, hi) = input.split_once('-').unwrap();
+            let range = lo.parse::<u64>().unwrap()..=hi.parse::<u64>().unwrap();
+
+            assert_eq!(super::part1(&input), Ok(range.clone().filter(|n| valid(*n, false)).count() as u64), "seed {}", seed);
+            assert_eq!(super::part2(&input), Ok(range.filter(|n| valid(*n, true)).count() as u64), "seed {}", seed);
+        }
+    }
 }
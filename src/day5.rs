@@ -1,7 +1,6 @@
-use std::num::ParseIntError;
 use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
+use crate::error::{number, parse_fields, ParseError};
 
 #[derive(Clone, Copy)]
 enum Parameter {
@@ -43,10 +42,10 @@ impl Parameter {
 struct Program(Vec<i64>);
 
 impl FromStr for Program {
-    type Err = ParseIntError;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.split(',').map(|v| v.parse::<i64>()).collect::<Result<Vec<_>, _>>().map(|r| Program(r))
+        parse_fields(s, ',', number).map(Program)
     }
 }
 
@@ -131,14 +130,12 @@ impl Program {
     }
 }
 
-#[aoc(day5, part1)]
-pub fn part1(input: &str) -> Result<i64, ParseIntError> {
+pub fn part1(input: &str) -> Result<i64, ParseError> {
     let program = input.parse::<Program>()?;
     Ok(*program.run(vec![1]).last().unwrap())
 }
 
-#[aoc(day5, part2)]
-pub fn part2(input: &str) -> Result<i64, ParseIntError> {
+pub fn part2(input: &str) -> Result<i64, ParseError> {
     let program = input.parse::<Program>()?;
     Ok(program.run(vec![5])[0])
 }
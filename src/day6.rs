@@ -1,97 +1,254 @@
-use std::collections::HashMap;
-use std::num::ParseIntError;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
+use crate::error::{column_of, parse_lines, ErrorKind, ParseError, SolveError};
+use crate::svg::Svg;
 
+/// An object by its name of up to four ASCII characters, packed into a number.
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
-struct Id(u32);
+pub struct Id(u32);
 
 impl Id {
-    fn new(name: &str) -> Id {
+    /// Panics on names that `parse` rejects, so is meant for literals like `Id::new("COM")`.
+    pub fn new(name: &str) -> Id {
+        assert!(!name.is_empty());
         assert!(name.len() <= 4);
         assert!(name.is_ascii());
         Id(name.as_bytes().iter().fold(0, |mem, byte| mem << 8 | (*byte as u32)))
     }
+
+    pub fn parse(name: &str) -> Result<Id, ParseError> {
+        if name.is_empty() {
+            return Err(ParseError::new(ErrorKind::Missing("object name")));
+        }
+
+        if let Some((i, c)) = name.char_indices().find(|(_, c)| !c.is_ascii()) {
+            return Err(ParseError::new(ErrorKind::UnexpectedChar(c)).at(1, column_of(name, &name[i..])));
+        }
+
+        if name.len() > 4 {
+            return Err(ParseError::new(ErrorKind::Invalid(format!("object name `{}` is longer than 4 characters", name))));
+        }
+
+        Ok(Id::new(name))
+    }
+
+    pub fn name(self) -> String {
+        self.0.to_be_bytes().iter().filter(|byte| **byte != 0).map(|byte| *byte as char).collect()
+    }
 }
 
-struct Map {
+/// The orbits of the puzzle input, one `CENTER)ORBITER` pair per line.
+pub struct Map {
+    /// The object each orbiting object directly orbits.
     orbits: HashMap::<Id, Id>
 }
 
 impl FromStr for Map {
-    type Err = ParseIntError;
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Map, ParseError> {
+        let lines = parse_lines(s, |line| {
+            let (center, orbiter) = line.split_once(')').ok_or_else(|| ParseError::new(ErrorKind::Missing("`)`")).at(1, line.chars().count() + 1))?;
+
+            Ok((Id::parse(orbiter).map_err(|err| err.at(1, column_of(line, orbiter)))?, Id::parse(center)?))
+        })?;
 
-    fn from_str(s: &str) -> Result<Map, ParseIntError> {
-        let mut orbits = HashMap::new();
+        let map = Map { orbits: lines.iter().copied().collect() };
 
-        for line in s.split_whitespace() {
-            let parts: Vec<&str> = line.split(')').collect();
-            orbits.insert(Id::new(parts[1]), Id::new(parts[0]));
+        if let Some(id) = map.find_loop(lines.iter().map(|(orbiter, _)| *orbiter)) {
+            let line = lines.iter().rposition(|(orbiter, _)| *orbiter == id).unwrap() + 1;
+
+            return Err(ParseError::new(ErrorKind::Invalid(format!("`{}` ends up orbiting itself", id.name()))).at(line, 1));
         }
 
-        Ok(Map { orbits })
+        Ok(map)
     }
 }
 
-#[aoc(day6, part1)]
-pub fn part1(input: &str) -> Result<usize, ParseIntError> {
-    let map = input.parse::<Map>()?;
-    let mut orbit_counts = HashMap::<Id, usize>::new();
+impl Map {
+    /// The object `id` directly orbits, which is nothing for the root.
+    pub fn center(&self, id: Id) -> Option<Id> {
+        self.orbits.get(&id).copied()
+    }
+
+    /// Every object that orbits another one, in no particular order.
+    pub fn orbiters(&self) -> impl Iterator<Item = Id> + '_ {
+        self.orbits.keys().copied()
+    }
+
+    /// The objects directly orbiting each object that has any, sorted by name.
+    pub fn satellites(&self) -> HashMap<Id, Vec<Id>> {
+        let mut satellites = HashMap::<Id, Vec<Id>>::new();
+
+        for (orbiter, center) in &self.orbits {
+            satellites.entry(*center).or_default().push(*orbiter);
+        }
+
+        for list in satellites.values_mut() {
+            list.sort_by_key(|id| id.name());
+        }
+
+        satellites
+    }
 
-    orbit_counts.insert(Id::new("COM"), 0);
+    /// Objects from `id` down to the root.
+    pub fn ancestors(&self, id: Id) -> Vec<Id> {
+        let mut result = vec![id];
 
-    fn get_count(orbit_counts: &mut HashMap<Id, usize>, orbits: &HashMap<Id, Id>, id: Id) -> usize {
-        match orbit_counts.get(&id) {
-            Some(count) => *count,
-            None => {
-                let count = get_count(orbit_counts, orbits, orbits[&id]) + 1;
-                orbit_counts.insert(id, count);
-                count
+        while let Some(center) = self.orbits.get(result.last().unwrap()) {
+            result.push(*center);
+        }
+
+        result
+    }
+
+    /// The first object found orbiting itself through other objects when walking down from each
+    /// of `starts` in turn.
+    fn find_loop(&self, starts: impl Iterator<Item = Id>) -> Option<Id> {
+        let mut settled = HashSet::new();
+
+        for start in starts {
+            let mut path = HashSet::new();
+            let mut id = start;
+
+            while !settled.contains(&id) {
+                if !path.insert(id) {
+                    return Some(id);
+                }
+
+                match self.orbits.get(&id) {
+                    Some(center) => id = *center,
+                    None => break,
+                }
             }
+
+            settled.extend(path);
         }
+
+        None
     }
 
-    Ok(map.orbits.keys().map(|k| get_count(&mut orbit_counts, &map.orbits, *k)).sum())
+    /// The direct and indirect orbits of every object together.
+    pub fn total_orbits(&self) -> usize {
+        fn get_count(orbit_counts: &mut HashMap<Id, usize>, orbits: &HashMap<Id, Id>, id: Id) -> usize {
+            match (orbit_counts.get(&id), orbits.get(&id)) {
+                (Some(count), _) => *count,
+                (None, None) => 0,
+                (None, Some(center)) => {
+                    let count = get_count(orbit_counts, orbits, *center) + 1;
+                    orbit_counts.insert(id, count);
+                    count
+                }
+            }
+        }
+
+        let mut orbit_counts = HashMap::<Id, usize>::new();
+
+        self.orbits.keys().map(|k| get_count(&mut orbit_counts, &self.orbits, *k)).sum()
+    }
+
+    /// Orbital transfers from the object `from` orbits to the object `to` orbits, or nothing when
+    /// either orbits nothing or they are not connected.
+    pub fn transfers(&self, from: Id, to: Id) -> Option<usize> {
+        let start = self.ancestors(self.center(from)?);
+        let steps = start.iter().enumerate().map(|(i, id)| (*id, i)).collect::<HashMap<_, _>>();
+
+        self.ancestors(self.center(to)?).iter().enumerate().find_map(|(i, id)| steps.get(id).map(|steps| i + steps))
+    }
 }
 
-#[aoc(day6, part2)]
-pub fn part2(input: &str) -> Result<usize, ParseIntError> {
+pub fn part1(input: &str) -> Result<usize, ParseError> {
+    input.parse::<Map>().map(|map| map.total_orbits())
+}
+
+pub fn part2(input: &str) -> Result<usize, SolveError> {
     let map = input.parse::<Map>()?;
 
-    let me = Id::new("YOU");
-    let root = Id::new("COM");
-    let santa = Id::new("SAN");
+    map.transfers(Id::new("YOU"), Id::new("SAN"))
+        .ok_or_else(|| SolveError::Unsolvable("no orbital transfers lead from `YOU` to `SAN`".to_string()))
+}
 
-    let start = map.orbits[&me];
-    let end = map.orbits[&santa];
+/// Places every object below `id`, one column per orbit depth. Leaves get consecutive rows and
+/// every other object sits level with the middle of its satellites. Returns the row of `id`.
+fn place(id: Id, depth: usize, satellites: &HashMap<Id, Vec<Id>>, positions: &mut HashMap<Id, (f64, f64)>, leaves: &mut f64) -> f64 {
+    let row = match satellites.get(&id) {
+        Some(list) => {
+            let rows = list.iter().map(|satellite| place(*satellite, depth + 1, satellites, positions, leaves)).collect::<Vec<_>>();
+            (rows[0] + rows[rows.len() - 1]) / 2.0
+        }
+        None => {
+            *leaves += 1.0;
+            *leaves - 1.0
+        }
+    };
 
-    let mut cache = HashMap::<Id, usize>::new();
+    positions.insert(id, (depth as f64, row));
+    row
+}
 
-    let mut pos = start;
-    for i in 0.. {
-        cache.insert(pos, i);
-        if pos == root { break; }
-        pos = map.orbits[&pos];
+/// Draws the orbit tree growing to the right from COM, with the transfers from YOU to SAN in red.
+pub fn render(input: &str) -> Result<Svg, ParseError> {
+    let map = input.parse::<Map>()?;
+    let satellites = map.satellites();
+    let mut roots = satellites.keys().filter(|id| map.center(**id).is_none()).copied().collect::<Vec<_>>();
+    let mut positions = HashMap::new();
+    let mut leaves = 0.0;
+
+    roots.sort_by_key(|id| id.0);
+
+    for root in roots {
+        place(root, 0, &satellites, &mut positions, &mut leaves);
     }
 
-    let mut pos = end;
-    for i in 0.. {
-        match cache.get(&pos) {
-            None => {
-                pos = map.orbits[&pos];
-            }
-            Some(value) => {
-                return Ok(i + value);
-            }
+    let you = map.ancestors(Id::new("YOU"));
+    let santa = map.ancestors(Id::new("SAN"));
+
+    // An orbit is travelled when exactly one of the two paths to the root goes through it
+    let transfers = you.iter().skip(1).chain(santa.iter().skip(1))
+        .filter(|id| !(you.contains(id) && santa.contains(id)))
+        .copied()
+        .collect::<HashSet<_>>();
+
+    let mut objects = positions.iter().map(|(id, position)| (*id, *position)).collect::<Vec<_>>();
+    let mut svg = Svg::around(objects.iter().map(|(_, position)| *position), 0.02);
+    let width = svg.extent() / 1500.0;
+
+    objects.sort_by_key(|(id, _)| id.0);
+    svg.title(&format!("Day 6: {} objects, {} orbital transfers", objects.len(), transfers.len()));
+
+    for (orbiter, position) in &objects {
+        if let Some(center) = map.center(*orbiter).and_then(|center| positions.get(&center)) {
+            let style = if transfers.contains(orbiter) {
+                format!(r#"stroke="red" stroke-width="{:.3}""#, width * 3.0)
+            } else {
+                format!(r#"stroke="gray" stroke-width="{:.3}""#, width)
+            };
+
+            svg.line(*center, *position, &style);
         }
     }
 
-    unreachable!();
+    for (id, position) in &objects {
+        let name = id.name();
+
+        if ["COM", "YOU", "SAN"].contains(&name.as_str()) {
+            svg.circle(*position, width * 4.0, r#"fill="red""#);
+            svg.text((position.0 + width * 6.0, position.1), &name, &format!(r#"font-size="{:.3}""#, width * 16.0));
+        } else {
+            svg.circle(*position, width * 1.5, r#"fill="black""#);
+        }
+    }
+
+    Ok(svg)
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::{HashMap, VecDeque};
+
+    use crate::generate::{self, Rng};
+
     #[test]
     fn part1() {
         assert_eq!(super::part1("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L"), Ok(42));
@@ -103,4 +260,92 @@ mod test {
         assert_eq!(super::part2("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN"), Ok(4));
         assert_eq!(super::part2("E)J\nJ)K\nC)D\nG)H\nD)E\nB)G\nCOM)B\nD)I\nB)C\nK)L\nE)F\nK)YOU\nI)SAN"), Ok(4));
     }
+
+    #[test]
+    fn map() {
+        let map = "COM)B\nB)C\nC)D\nB)YOU\nD)SAN".parse::<super::Map>().unwrap();
+        let [com, b, c] = ["COM", "B", "C"].map(super::Id::new);
+
+        assert_eq!(map.center(c), Some(b));
+        assert_eq!(map.center(com), None);
+        assert_eq!(map.ancestors(c), [c, b, com]);
+        assert_eq!(map.satellites()[&b], [c, super::Id::new("YOU")]);
+        assert_eq!("COM)B\nCOM)AA".parse::<super::Map>().unwrap().satellites()[&com], ["AA", "B"].map(super::Id::new));
+        assert_eq!(map.total_orbits(), 1 + 2 + 3 + 2 + 4);
+        assert_eq!(map.transfers(super::Id::new("YOU"), super::Id::new("SAN")), Some(2));
+        assert_eq!(super::part2("COM)B").unwrap_err().to_string(), "no orbital transfers lead from `YOU` to `SAN`");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = super::part1("COM)B\nB)CDEFG").unwrap_err();
+
+        assert_eq!((err.line, err.column), (2, 3));
+        assert_eq!(err.to_string(), "line 2, column 3: object name `CDEFG` is longer than 4 characters");
+        assert_eq!(super::part1("COM)B\nBC").unwrap_err().to_string(), "line 2, column 3: missing `)`");
+        assert_eq!(super::part1("COM)B\nB)").unwrap_err().to_string(), "line 2, column 3: missing object name");
+        assert_eq!(super::part1(")B").unwrap_err().to_string(), "line 1, column 1: missing object name");
+        assert_eq!(super::part1("COM)Bé").unwrap_err().to_string(), "line 1, column 6: unexpected character 'é'");
+        assert_eq!(super::part1("COM)B\nA)C\nC)A").unwrap_err().to_string(), "line 2, column 1: `C` ends up orbiting itself");
+    }
+
+    #[test]
+    fn render() {
+        let svg = super::render("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN").unwrap().to_string();
+
+        assert!(svg.contains("<title>Day 6: 14 objects, 4 orbital transfers</title>"));
+        assert_eq!(svg.matches(r#"stroke="red""#).count(), 4);
+        assert!(svg.contains(">YOU</text>"));
+    }
+
+    /// Counts every orbit by walking to `COM`, and transfers with a search over the orbits as an
+    /// undirected graph.
+    fn reference(input: &str) -> (usize, usize) {
+        let orbits = input.lines().map(|line| {
+            let (center, orbiter) = line.split_once(')').unwrap();
+            (orbiter, center)
+        }).collect::<HashMap<_, _>>();
+
+        let total = orbits.keys().map(|object| {
+            let mut count = 0;
+            let mut object = *object;
+
+            while let Some(center) = orbits.get(object) {
+                object = center;
+                count += 1;
+            }
+
+            count
+        }).sum();
+
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        distances.insert(orbits["YOU"], 0);
+        queue.push_back(orbits["YOU"]);
+
+        while let Some(object) = queue.pop_front() {
+            let neighbours = orbits.iter().filter(|(_, center)| **center == object).map(|(orbiter, _)| *orbiter).chain(orbits.get(object).copied());
+
+            for neighbour in neighbours.collect::<Vec<_>>() {
+                if !distances.contains_key(neighbour) {
+                    distances.insert(neighbour, distances[object] + 1);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        (total, distances[orbits["SAN"]])
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..50 {
+            let input = generate::orbit_map(&mut Rng::new(seed), 200);
+            let (total, transfers) = reference(&input);
+
+            assert_eq!(super::part1(&input), Ok(total), "seed {}", seed);
+            assert_eq!(super::part2(&input), Ok(transfers), "seed {}", seed);
+        }
+    }
 }
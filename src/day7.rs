@@ -1,276 +1,115 @@
-use std::collections::VecDeque;
-use std::num::ParseIntError;
-use std::str::FromStr;
+use std::fmt;
 
-use aoc_runner_derive::aoc;
+use crate::combinatorics::permutations;
+use crate::error::SolveError;
+use crate::intcode::network::Network;
+use crate::intcode::{Process, Program, StopReason};
+use crate::json::Value;
+use crate::json_object;
+use crate::parallel;
+use crate::runner::Solution;
 
-#[derive(Clone, Copy)]
-enum Parameter {
-    Position(usize),
-    Immediate(i64),
-}
+/// Runs one amplifier per phase setting in a chain, or a ring when `feedback` is set, and
+/// returns the last signal that reaches the thrusters. Amplifiers are forked from `template`.
+fn amplify(template: &Process, phases: &[i64], feedback: bool) -> Result<i64, String> {
+    let mut network = Network::new();
 
-impl Parameter {
-    fn new(mode: i64, value: i64) -> Parameter {
-        match mode {
-            0 => Parameter::Position(value as usize),
-            1 => Parameter::Immediate(value),
-            _ => panic!("Invalid parameter mode {}", mode),
-        }
+    let amplifiers = phases.iter().map(|phase| {
+        let amplifier = network.add(template.fork());
+        network.feed(amplifier, *phase);
+        amplifier
+    }).collect::<Vec<_>>();
+
+    if feedback {
+        network.ring(&amplifiers);
+    } else {
+        network.chain(&amplifiers);
     }
 
-    fn read(memory: &[i64], eip: usize, offset: usize) -> Parameter {
-        let mode = (memory[eip] / 10i64.pow(1 + (offset as u32))) % 10;
-        let value = memory[eip + offset];
+    let (first, last) = match (amplifiers.first(), amplifiers.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return Err("there are no amplifiers to run".to_string()),
+    };
 
-        Parameter::new(mode, value)
-    }
+    network.feed(first, 0);
 
-    fn load(self, memory: &[i64]) -> i64 {
-        match self {
-            Parameter::Position(pos) => memory[pos],
-            Parameter::Immediate(value) => value,
-        }
+    match network.run() {
+        StopReason::Halted => {}
+        reason => return Err(format!("the amplifiers stopped without halting: {}", reason)),
     }
 
-    fn store(self, value: i64, memory: &mut [i64]) {
-        match self {
-            Parameter::Position(pos) => memory[pos] = value,
-            Parameter::Immediate(_) => panic!("Cannot store to an immediate mode parameter"),
-        }
-    }
+    network.outputs(last).last().copied().ok_or_else(|| "the last amplifier sent no signal".to_string())
 }
 
-struct Process {
-    memory: Vec<i64>,
-    eip: usize,
-    input_buffer: VecDeque<i64>,
-    output_buffer: VecDeque<i64>,
-}
+/// Best ordering of `phases` and the thrust it produces, searched on `threads` workers. Fails if
+/// any ordering does not get a signal to the thrusters.
+pub fn best_phases(program: &Program, phases: &[i64], feedback: bool, threads: usize) -> Result<(Vec<i64>, i64), SolveError> {
+    let configurations = permutations(phases).collect::<Vec<_>>();
 
-#[derive(PartialEq)]
-enum ProcessRunResult {
-    Complete,
-    WouldBlock,
+    // An error sorts above every thrust, so it wins the search over any ordering that worked
+    match parallel::best_by_key(&configurations, threads, || program.spawn(), |template, config| amplify(template, config, feedback)) {
+        Some((index, Ok(thrust))) => Ok((configurations[index].clone(), thrust)),
+        Some((_, Err(message))) => Err(SolveError::Unsolvable(message)),
+        None => Err(SolveError::Unsolvable("there are no phase settings to try".to_string())),
+    }
 }
 
-impl Process {
-    fn feed(&mut self, value: i64) {
-        self.input_buffer.push_back(value);
-    }
+/// The winning phase settings and the thrust they produce.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Amplification {
+    pub phases: Vec<i64>,
+    pub thrust: i64,
+}
 
-    fn read(&mut self) -> Option<i64> {
-        self.output_buffer.pop_front()
+impl fmt::Display for Amplification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.thrust)
     }
+}
 
-    fn run(&mut self) -> ProcessRunResult {
-        loop {
-            match self.memory[self.eip] % 100 {
-                1 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(lhs.load(&self.memory) + rhs.load(&self.memory), &mut self.memory);
-                    self.eip += 4;
-                }
-                2 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(lhs.load(&self.memory) * rhs.load(&self.memory), &mut self.memory);
-                    self.eip += 4;
-                }
-                3 => {
-                    let input = match self.input_buffer.pop_front() {
-                        None => return ProcessRunResult::WouldBlock,
-                        Some(value) => value,
-                    };
-
-                    let out = Parameter::read(&self.memory, self.eip, 1);
-                    out.store(input, &mut self.memory);
-                    self.eip += 2;
-                }
-                4 => {
-                    let src = Parameter::read(&self.memory, self.eip, 1);
-                    self.output_buffer.push_back(src.load(&self.memory));
-                    self.eip += 2;
-                }
-                5 => {
-                    let test = Parameter::read(&self.memory, self.eip, 1);
-                    let jump = Parameter::read(&self.memory, self.eip, 2);
-
-                    if test.load(&self.memory) != 0 {
-                        self.eip = jump.load(&self.memory) as usize;
-                    } else {
-                        self.eip += 3;
-                    }
-                }
-                6 => {
-                    let test = Parameter::read(&self.memory, self.eip, 1);
-                    let jump = Parameter::read(&self.memory, self.eip, 2);
-
-                    if test.load(&self.memory) == 0 {
-                        self.eip = jump.load(&self.memory) as usize;
-                    } else {
-                        self.eip += 3;
-                    }
-                }
-                7 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(if lhs.load(&self.memory) < rhs.load(&self.memory) { 1 } else { 0 }, &mut self.memory);
-                    self.eip += 4;
-                }
-                8 => {
-                    let lhs = Parameter::read(&self.memory, self.eip, 1);
-                    let rhs = Parameter::read(&self.memory, self.eip, 2);
-                    let out = Parameter::read(&self.memory, self.eip, 3);
-                    out.store(if lhs.load(&self.memory) == rhs.load(&self.memory) { 1 } else { 0 }, &mut self.memory);
-                    self.eip += 4;
-                }
-                99 => {
-                    return ProcessRunResult::Complete;
-                }
-                op => {
-                    panic!("Unknown op code: {}", op);
-                }
-            }
-        }
+impl Solution for Amplification {
+    fn report(&self) -> Option<Value> {
+        Some(json_object! { "phases" => self.phases.clone(), "thrust" => self.thrust })
     }
 }
 
-struct Program(Vec<i64>);
+fn amplification(input: &str, phases: &[i64], feedback: bool) -> Result<Amplification, SolveError> {
+    let program = input.parse::<Program>()?;
+    let (phases, thrust) = best_phases(&program, phases, feedback, parallel::available_threads())?;
 
-impl FromStr for Program {
-    type Err = ParseIntError;
+    Ok(Amplification { phases, thrust })
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.split(',').map(|v| v.parse::<i64>()).collect::<Result<Vec<_>, _>>().map(|r| Program(r))
-    }
+pub fn best_chain(input: &str) -> Result<Amplification, SolveError> {
+    amplification(input, &[0, 1, 2, 3, 4], false)
 }
 
-impl Program {
-    fn spawn(&self) -> Process {
-        Process {
-            memory: self.0.clone(),
-            eip: 0,
-            input_buffer: VecDeque::new(),
-            output_buffer: VecDeque::new(),
-        }
-    }
+pub fn best_feedback_loop(input: &str) -> Result<Amplification, SolveError> {
+    amplification(input, &[5, 6, 7, 8, 9], true)
 }
 
-fn permutations(mut values: [i64; 5]) -> Vec<[i64; 5]> {
-    fn inner(out: &mut Vec<[i64; 5]>, data: &mut [i64; 5], l: usize, r: usize) {
-        if l == r {
-            out.push(data.clone());
-        } else {
-            for i in l..=r {
-                data.swap(l, i);
-                inner(out, data, l + 1, r);
-                data.swap(l, i);
-            }
-        }
-    }
+pub fn part1(input: &str) -> Result<i64, SolveError> {
+    best_chain(input).map(|amplification| amplification.thrust)
+}
 
-    let mut result = Vec::new();
-    inner(&mut result, &mut values, 0, 4);
-    result
+pub fn part2(input: &str) -> Result<i64, SolveError> {
+    best_feedback_loop(input).map(|amplification| amplification.thrust)
 }
 
-#[aoc(day7, part1)]
-pub fn part1(input: &str) -> Result<i64, ParseIntError> {
+pub fn part1_serial(input: &str) -> Result<i64, SolveError> {
     let program = input.parse::<Program>()?;
-    let configurations = permutations([0, 1, 2, 3, 4]);
-
-    let mut max = 0;
-
-    for config in configurations {
-        let mut a = program.spawn();
-        let mut b = program.spawn();
-        let mut c = program.spawn();
-        let mut d = program.spawn();
-        let mut e = program.spawn();
-
-        a.feed(config[0]);
-        b.feed(config[1]);
-        c.feed(config[2]);
-        d.feed(config[3]);
-        e.feed(config[4]);
-
-        a.feed(0);
-        a.run();
-        b.feed(a.read().unwrap());
-        b.run();
-        c.feed(b.read().unwrap());
-        c.run();
-        d.feed(c.read().unwrap());
-        d.run();
-        e.feed(d.read().unwrap());
-        e.run();
-        let result = e.read().unwrap();
-
-        if result > max {
-            max = result;
-        }
-    }
 
-    Ok(max)
+    Ok(best_phases(&program, &[0, 1, 2, 3, 4], false, 1)?.1)
 }
 
-#[aoc(day7, part2)]
-pub fn part2(input: &str) -> Result<i64, ParseIntError> {
+pub fn part2_serial(input: &str) -> Result<i64, SolveError> {
     let program = input.parse::<Program>()?;
-    let configurations = permutations([5, 6, 7, 8, 9]);
-
-    let mut max = 0;
-
-    for config in configurations {
-        let mut a = program.spawn();
-        let mut b = program.spawn();
-        let mut c = program.spawn();
-        let mut d = program.spawn();
-        let mut e = program.spawn();
-
-        a.feed(config[0]);
-        b.feed(config[1]);
-        c.feed(config[2]);
-        d.feed(config[3]);
-        e.feed(config[4]);
-
-        let mut last: i64 = 0;
-        loop {
-            a.feed(last);
-            a.run();
-            b.feed(a.read().unwrap());
-            b.run();
-            c.feed(b.read().unwrap());
-            c.run();
-            d.feed(c.read().unwrap());
-            d.run();
-            e.feed(d.read().unwrap());
-            e.run();
-
-            last = e.read().unwrap();
-
-            if e.run() == ProcessRunResult::Complete {
-                break;
-            }
-        }
 
-        if last > max {
-            max = last;
-        }
-    }
-
-    Ok(max)
+    Ok(best_phases(&program, &[5, 6, 7, 8, 9], true, 1)?.1)
 }
 
 #[cfg(test)]
 mod test {
-    use super::Program;
-
     fn factorial(num: usize) -> usize {
         match num {
             0 => 1,
@@ -278,73 +117,63 @@ mod test {
         }
     }
 
-    fn run(source: &str, input: Vec<i64>) -> Vec<i64> {
-        let program = source.parse::<Program>().unwrap();
-
-        let mut process = program.spawn();
-        let mut input = input.iter();
-        let mut output = Vec::new();
-
-        loop {
-            match process.run() {
-                super::ProcessRunResult::Complete => break,
-                super::ProcessRunResult::WouldBlock => process.input_buffer.push_back(*input.next().unwrap()),
-            }
-        }
-
-        while let Some(foo) = process.output_buffer.pop_front() {
-            output.push(foo);
-        }
-
-        output
-    }
-
     #[test]
     fn permutations() {
         use std::collections::HashSet;
 
-        let permutations = super::permutations([0, 1, 2, 3, 4]);
+        use crate::combinatorics::{combinations, lexicographic_permutations, permutations, product};
 
-        assert_eq!(permutations.len(), factorial(5));
+        for n in 0..7 {
+            let values = (0..n as i64).collect::<Vec<_>>();
+            let heap = permutations(&values).collect::<HashSet<_>>();
+            let lexicographic = lexicographic_permutations(&values).collect::<HashSet<_>>();
 
-        let mut seen = HashSet::<[i64; 5]>::new();
-        for value in permutations { seen.insert(value); }
+            assert_eq!(heap.len(), factorial(n));
+            assert_eq!(heap, lexicographic);
 
-        assert_eq!(seen.len(), factorial(5));
+            for k in 0..=n {
+                assert_eq!(combinations(&values, k).count(), factorial(n) / (factorial(k) * factorial(n - k)));
+            }
+        }
+
+        assert_eq!(product(&[[0, 1, 2, 3, 4], [5, 6, 7, 8, 9]]).count(), 25);
     }
 
     #[test]
-    fn day5_part1() {
-        assert_eq!(run("1002,4,3,4,33", vec![]), vec![]);
-        assert_eq!(run("1101,100,-1,4,0", vec![]), vec![]);
-        assert_eq!(run("1002,6,3,6,4,0,33", vec![]), vec![1002]);
-        assert_eq!(run("3,5,4,5,99,0", vec![-16]), vec![-16]);
+    fn day7_part1() {
+        assert_eq!(super::part1("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0"), Ok(43210));
     }
 
     #[test]
-    fn day5_part2() {
-        assert_eq!(run("3,9,8,9,10,9,4,9,99,-1,8", vec![7]), vec![0]);
-        assert_eq!(run("3,9,8,9,10,9,4,9,99,-1,8", vec![8]), vec![1]);
-
-        assert_eq!(run("3,9,7,9,10,9,4,9,99,-1,8", vec![7]), vec![1]);
-        assert_eq!(run("3,9,7,9,10,9,4,9,99,-1,8", vec![8]), vec![0]);
-
-        assert_eq!(run("3,3,1108,-1,8,3,4,3,99", vec![7]), vec![0]);
-        assert_eq!(run("3,3,1108,-1,8,3,4,3,99", vec![8]), vec![1]);
+    fn phase_taint() {
+        use crate::intcode::taint::TaintTracker;
 
-        assert_eq!(run("3,3,1107,-1,8,3,4,3,99", vec![7]), vec![1]);
-        assert_eq!(run("3,3,1107,-1,8,3,4,3,99", vec![8]), vec![0]);
+        let program = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0".parse::<super::Program>().unwrap();
+        let mut amplifier = TaintTracker::new(program.spawn());
 
-        assert_eq!(run("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", vec![0]), vec![0]);
-        assert_eq!(run("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", vec![2]), vec![1]);
+        let phase = amplifier.feed(4);
+        let signal = amplifier.feed(0);
+        amplifier.run();
 
-        assert_eq!(run("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", vec![0]), vec![0]);
-        assert_eq!(run("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", vec![2]), vec![1]);
+        assert_eq!(amplifier.outputs()[0].value, 4);
+        assert_eq!(amplifier.outputs()[0].sources, vec![phase, signal].into_iter().collect());
     }
 
     #[test]
-    fn day7_part1() {
-        assert_eq!(super::part1("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0"), Ok(43210));
+    fn best_configuration() {
+        let program = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0".parse::<super::Program>().unwrap();
+
+        assert_eq!(super::best_phases(&program, &[0, 1, 2, 3, 4], false, 3), Ok((vec![4, 3, 2, 1, 0], 43210)));
+
+        let err = super::best_phases(&program, &[], false, 3).unwrap_err();
+        assert_eq!(err.to_string(), "there are no amplifiers to run");
+
+        // Amplifiers with phase 2 run into an unknown opcode
+        let program = "3,13,1008,13,2,14,1005,14,12,4,13,99,55,0,0".parse::<super::Program>().unwrap();
+
+        assert_eq!(super::best_phases(&program, &[0, 1], false, 1), Ok((vec![0, 1], 1)));
+        assert_eq!(super::best_phases(&program, &[0, 1, 2], false, 3).unwrap_err().to_string(), "the amplifiers stopped without halting: unknown opcode 55");
+        assert_eq!(super::part1("3,7,3,7,3,7,99,0").unwrap_err().to_string(), "the amplifiers stopped without halting: waiting for input");
     }
 
     #[test]
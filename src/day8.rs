@@ -1,8 +1,10 @@
 use std::fmt;
-use std::num::ParseIntError;
 use std::str::FromStr;
 
-use aoc_runner_derive::aoc;
+use crate::error::{ErrorKind, ParseError};
+use crate::json::Value;
+use crate::json_object;
+use crate::runner::Solution;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Pixel {
@@ -11,16 +13,13 @@ pub enum Pixel {
     Transparent,
 }
 
-impl FromStr for Pixel {
-    type Err = ParseIntError;
-
-    fn from_str(s: &str) -> Result<Pixel, ParseIntError> {
-        match u8::from_str(s) {
-            Ok(0) => Ok(Pixel::Black),
-            Ok(1) => Ok(Pixel::White),
-            Ok(2) => Ok(Pixel::Transparent),
-            Ok(_) => panic!("Invalid pixel"),
-            Err(err) => Err(err),
+impl Pixel {
+    fn from_char(c: char) -> Result<Pixel, ParseError> {
+        match c {
+            '0' => Ok(Pixel::Black),
+            '1' => Ok(Pixel::White),
+            '2' => Ok(Pixel::Transparent),
+            _ => Err(ParseError::new(ErrorKind::UnexpectedChar(c))),
         }
     }
 }
@@ -37,7 +36,7 @@ impl Layer {
 
 impl fmt::Display for Layer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "")?;
+        writeln!(f)?;
 
         for row in self.pixels.chunks(25) {
             for px in row.iter() {
@@ -48,13 +47,26 @@ impl fmt::Display for Layer {
                 }
             }
 
-            writeln!(f, "")?;
+            writeln!(f)?;
         }
 
         Ok(())
     }
 }
 
+/// Rows drawn with `#` for white, `.` for black and a space for transparent pixels.
+impl Solution for Layer {
+    fn report(&self) -> Option<Value> {
+        let rows = self.pixels.chunks(25).map(|row| row.iter().map(|px| match px {
+            Pixel::Black => '.',
+            Pixel::White => '#',
+            Pixel::Transparent => ' ',
+        }).collect::<String>()).collect::<Vec<_>>();
+
+        Some(json_object! { "width" => 25, "height" => rows.len(), "rows" => rows })
+    }
+}
+
 struct Image {
     layers: Vec<Layer>
 }
@@ -76,25 +88,70 @@ impl Image {
 }
 
 impl FromStr for Image {
-    type Err = ParseIntError;
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Image, ParseError> {
+        let data = s.chars().enumerate().map(|(i, c)| Pixel::from_char(c).map_err(|err| err.at(1, i + 1))).collect::<Result<Vec<_>, _>>()?;
+
+        if data.is_empty() || data.len() % (25 * 6) != 0 {
+            return Err(ParseError::new(ErrorKind::Invalid(format!("{} pixels do not make whole 25x6 layers", data.len()))).at(1, data.len() + 1));
+        }
 
-    fn from_str(s: &str) -> Result<Image, ParseIntError> {
-        let data = s.chars().map(|c| Pixel::from_str(&c.to_string())).collect::<Result<Vec<_>, _>>()?;
         let layers = data.chunks(25 * 6).map(|chunk| Layer { pixels: chunk.to_owned() }).collect();
 
         Ok(Image { layers })
     }
 }
 
-#[aoc(day8, part1)]
-pub fn part1(input: &str) -> Result<usize, ParseIntError> {
+pub fn part1(input: &str) -> Result<usize, ParseError> {
     let img = input.parse::<Image>()?;
     let layer = img.layers.iter().min_by_key(|layer| layer.count_pixels(Pixel::Black)).unwrap();
 
     Ok(layer.count_pixels(Pixel::White) * layer.count_pixels(Pixel::Transparent))
 }
 
-#[aoc(day8, part2)]
-pub fn part2(input: &str) -> Result<Layer, ParseIntError> {
+pub fn part2(input: &str) -> Result<Layer, ParseError> {
     input.parse::<Image>().map(|img| img.flatten())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::generate::{self, Rng};
+    use crate::runner::Solution;
+
+    #[test]
+    fn parse_errors() {
+        let mut input = "0".repeat(150);
+
+        input.replace_range(30..31, "3");
+
+        assert_eq!(super::part1(&input).unwrap_err().to_string(), "line 1, column 31: unexpected character '3'");
+        assert_eq!(super::part1("012").unwrap_err().to_string(), "line 1, column 4: 3 pixels do not make whole 25x6 layers");
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..50 {
+            let input = generate::image(&mut Rng::new(seed), 25, 6, 20);
+            let layers = input.as_bytes().chunks(25 * 6).collect::<Vec<_>>();
+            let count = |layer: &[u8], digit: u8| layer.iter().filter(|pixel| **pixel == digit).count();
+            let mut fewest_zeros = layers[0];
+
+            for layer in &layers {
+                if count(layer, b'0') < count(fewest_zeros, b'0') {
+                    fewest_zeros = layer;
+                }
+            }
+
+            let rows = (0..6).map(|y| (0..25).map(|x| {
+                match layers.iter().map(|layer| layer[y * 25 + x]).find(|pixel| *pixel != b'2') {
+                    Some(b'0') => '.',
+                    _ => '#',
+                }
+            }).collect::<String>()).collect::<Vec<_>>();
+
+            assert_eq!(super::part1(&input), Ok(count(fewest_zeros, b'1') * count(fewest_zeros, b'2')), "seed {}", seed);
+            assert_eq!(super::part2(&input).unwrap().report().unwrap().get("rows"), Some(&rows.into()), "seed {}", seed);
+        }
+    }
+}
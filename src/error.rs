@@ -0,0 +1,151 @@
+//! The error returned by every puzzle parser. Lines and columns start at 1 and count characters.
+//!
+//! Parsers of small pieces, like one instruction, report positions relative to that piece and the
+//! caller moves them into place with `ParseError::at`. Input that parses but has no answer is a
+//! `SolveError` instead, which carries no position.
+
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    Number(ParseIntError),
+    UnexpectedChar(char),
+    /// The input ended before the named part.
+    Missing(&'static str),
+    /// Well-formed input that breaks a rule of the puzzle.
+    Invalid(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Number(err) => write!(f, "{}", err),
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ErrorKind::Missing(what) => write!(f, "missing {}", what),
+            ErrorKind::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+impl ParseError {
+    /// An error at the start of the input.
+    pub fn new(kind: ErrorKind) -> ParseError {
+        ParseError { line: 1, column: 1, kind }
+    }
+
+    /// Moves an error found in a piece of input that starts at `line` and `column` of the whole.
+    pub fn at(self, line: usize, column: usize) -> ParseError {
+        ParseError {
+            column: if self.line == 1 { column + self.column - 1 } else { self.column },
+            line: line + self.line - 1,
+            kind: self.kind,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Number(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseIntError> for ParseError {
+    fn from(err: ParseIntError) -> ParseError {
+        ParseError::new(ErrorKind::Number(err))
+    }
+}
+
+/// Why a solver has no answer: its input does not parse, or the puzzle cannot be solved for it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolveError {
+    Parse(ParseError),
+    Unsolvable(String),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::Parse(err) => write!(f, "{}", err),
+            SolveError::Unsolvable(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for SolveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SolveError::Parse(err) => Some(err),
+            SolveError::Unsolvable(_) => None,
+        }
+    }
+}
+
+impl From<ParseError> for SolveError {
+    fn from(err: ParseError) -> SolveError {
+        SolveError::Parse(err)
+    }
+}
+
+pub fn number<T: FromStr<Err = ParseIntError>>(s: &str) -> Result<T, ParseError> {
+    s.parse().map_err(ParseError::from)
+}
+
+/// Column at which `part` starts, which has to be a slice of `whole`.
+pub fn column_of(whole: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - whole.as_ptr() as usize;
+
+    whole[..offset].chars().count() + 1
+}
+
+/// Parses every `separator` separated field of a single line.
+pub fn parse_fields<T, F>(s: &str, separator: char, parse: F) -> Result<Vec<T>, ParseError>
+where
+    F: Fn(&str) -> Result<T, ParseError>,
+{
+    s.split(separator).map(|field| parse(field).map_err(|err| err.at(1, column_of(s, field)))).collect()
+}
+
+pub fn parse_lines<T, F>(s: &str, parse: F) -> Result<Vec<T>, ParseError>
+where
+    F: Fn(&str) -> Result<T, ParseError>,
+{
+    s.lines().enumerate().map(|(i, line)| parse(line).map_err(|err| err.at(i + 1, 1))).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{column_of, number, parse_fields, parse_lines, ErrorKind, ParseError};
+
+    #[test]
+    fn positions() {
+        let err = parse_lines("1,2\n3,x,5", |line| parse_fields(line, ',', number::<u8>)).unwrap_err();
+
+        assert_eq!((err.line, err.column), (2, 3));
+        assert!(matches!(err.kind, ErrorKind::Number(_)));
+        assert_eq!(err.to_string(), "line 2, column 3: invalid digit found in string");
+
+        let err = ParseError::new(ErrorKind::Missing("wire")).at(3, 1).at(2, 5);
+
+        assert_eq!((err.line, err.column), (4, 1));
+        assert_eq!(column_of("ab→cd", &"ab→cd"[5..]), 4);
+    }
+}
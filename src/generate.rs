@@ -0,0 +1,236 @@
+//! Seeded random puzzle inputs, so the solvers can be checked against slow but obvious reference
+//! solutions on many more inputs than the examples. The same seed always gives the same input.
+
+/// SplitMix64, which is plenty for test inputs and needs no dependency.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `lo..=hi`. The slight bias towards low numbers does not matter here.
+    pub fn range(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo <= hi);
+
+        match (hi - lo).checked_add(1) {
+            Some(span) => lo + self.next_u64() % span,
+            None => self.next_u64(),
+        }
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+
+        self.range(0, n as u64 - 1) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Day 1: one module mass per line.
+pub fn masses(rng: &mut Rng, count: usize) -> String {
+    (0..count).map(|_| rng.range(1_000, 200_000).to_string()).collect::<Vec<_>>().join("\n")
+}
+
+/// A straight piece of wire as the line it is on and the range it covers along that line.
+type Segment = (i64, i64, i64);
+
+/// Horizontal segments as `(y, x_lo, x_hi)` and vertical ones as `(x, y_lo, y_hi)`.
+fn segments(vertices: &[(i64, i64)]) -> (Vec<Segment>, Vec<Segment>) {
+    let mut horizontal = Vec::new();
+    let mut vertical = Vec::new();
+
+    for pair in vertices.windows(2) {
+        let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+
+        if y1 == y2 {
+            horizontal.push((y1, x1.min(x2), x1.max(x2)));
+        } else {
+            vertical.push((x1, y1.min(y2), y1.max(y2)));
+        }
+    }
+
+    (horizontal, vertical)
+}
+
+/// Whether parallel segments of the two wires share a point other than the central port.
+fn run_along(lhs: &[Segment], rhs: &[Segment]) -> bool {
+    lhs.iter().any(|&(line, lo, hi)| rhs.iter().any(|&(other_line, other_lo, other_hi)| {
+        let (lo, hi) = (lo.max(other_lo), hi.min(other_hi));
+
+        line == other_line && lo <= hi && !(line == 0 && lo == 0 && hi == 0)
+    }))
+}
+
+/// Day 3: two wires of `count` instructions each, turning at every corner. The puzzle only
+/// defines wires that cross each other, so wires that run along each other are never returned.
+pub fn wires(rng: &mut Rng, count: usize) -> String {
+    loop {
+        let mut lines = Vec::new();
+        let mut parallel = Vec::new();
+
+        for _ in 0..2 {
+            let mut position = (0, 0);
+            let mut vertices = vec![position];
+            let mut instructions = Vec::new();
+            let mut vertical = rng.below(2) == 0;
+
+            for _ in 0..count {
+                let distance = rng.range(1, 100) as i64;
+                let forward = rng.below(2) == 0;
+                let direction = match (vertical, forward) {
+                    (true, true) => { position.1 += distance; 'U' }
+                    (true, false) => { position.1 -= distance; 'D' }
+                    (false, true) => { position.0 += distance; 'R' }
+                    (false, false) => { position.0 -= distance; 'L' }
+                };
+
+                instructions.push(format!("{}{}", direction, distance));
+                vertices.push(position);
+                vertical = !vertical;
+            }
+
+            lines.push(instructions.join(","));
+            parallel.push(segments(&vertices));
+        }
+
+        if !run_along(&parallel[0].0, &parallel[1].0) && !run_along(&parallel[0].1, &parallel[1].1) {
+            return lines.join("\n");
+        }
+    }
+}
+
+/// Day 4: a range of six digit passwords at most `width` wide.
+pub fn password_range(rng: &mut Rng, width: u64) -> String {
+    let lo = rng.range(100_000, 999_999);
+    let hi = rng.range(lo, (lo + width).min(999_999));
+
+    format!("{}-{}", lo, hi)
+}
+
+fn object_name(mut index: usize) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    let mut name = Vec::new();
+
+    loop {
+        name.push(DIGITS[index % DIGITS.len()]);
+        index /= DIGITS.len();
+
+        if index == 0 { break; }
+    }
+
+    assert!(name.len() <= 4, "too many objects");
+    name.iter().rev().map(|byte| *byte as char).collect()
+}
+
+/// Day 6: `COM` and `count` other objects orbiting it as a tree, including `YOU` and `SAN` which
+/// nothing orbits. The orbits are listed in random order.
+pub fn orbit_map(rng: &mut Rng, count: usize) -> String {
+    assert!(count >= 3);
+
+    let mut names = vec!["COM".to_string()];
+    let mut orbits = Vec::new();
+
+    for i in 0..count - 2 {
+        let center = names[rng.below(names.len())].clone();
+        let name = object_name(i);
+
+        orbits.push(format!("{}){}", center, name));
+        names.push(name);
+    }
+
+    for name in &["YOU", "SAN"] {
+        orbits.push(format!("{}){}", names[rng.below(names.len())], name));
+    }
+
+    rng.shuffle(&mut orbits);
+    orbits.join("\n")
+}
+
+/// Day 8: `layers` layers of `width` by `height` pixels. The last layer has no transparent
+/// pixels so the decoded image never has any either.
+pub fn image(rng: &mut Rng, width: usize, height: usize, layers: usize) -> String {
+    assert!(layers > 0);
+
+    (0..layers).flat_map(|layer| {
+        let colors = if layer + 1 == layers { 2 } else { 3 };
+
+        (0..width * height).map(|_| (b'0' + rng.below(colors) as u8) as char).collect::<Vec<_>>()
+    }).collect()
+}
+
+/// Day 10: `asteroids` asteroids scattered over a `width` by `height` field.
+pub fn asteroid_field(rng: &mut Rng, width: usize, height: usize, asteroids: usize) -> String {
+    assert!(asteroids <= width * height);
+
+    let mut cells = (0..width * height).map(|i| i < asteroids).collect::<Vec<_>>();
+
+    rng.shuffle(&mut cells);
+    cells.chunks(width).map(|row| row.iter().map(|asteroid| if *asteroid { '#' } else { '.' }).collect::<String>()).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::Rng;
+
+    #[test]
+    fn seeded() {
+        let first = (0..5).map(|_| Rng::new(7).next_u64()).collect::<Vec<_>>();
+        let mut rng = Rng::new(7);
+
+        assert!(first.iter().all(|n| *n == first[0]));
+        assert_ne!(rng.next_u64(), rng.next_u64());
+        assert_eq!(super::masses(&mut Rng::new(3), 10), super::masses(&mut Rng::new(3), 10));
+        assert_ne!(super::masses(&mut Rng::new(3), 10), super::masses(&mut Rng::new(4), 10));
+
+        let mut counts = HashMap::new();
+
+        for _ in 0..6000 {
+            *counts.entry(rng.range(1, 6)).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts.len(), 6);
+        assert!(counts.values().all(|count| (800..1200).contains(count)));
+    }
+
+    #[test]
+    fn shapes() {
+        let mut rng = Rng::new(1);
+        let map = super::orbit_map(&mut rng, 50);
+
+        assert_eq!(map.lines().count(), 50);
+        assert!(map.lines().any(|line| line.ends_with(")YOU")) && map.lines().any(|line| line.ends_with(")SAN")));
+        assert!(map.lines().any(|line| line.starts_with("COM)")));
+
+        let field = super::asteroid_field(&mut rng, 7, 3, 5);
+
+        assert_eq!(field.lines().map(str::len).collect::<Vec<_>>(), [7, 7, 7]);
+        assert_eq!(field.matches('#').count(), 5);
+        assert!(!super::image(&mut rng, 4, 2, 3)[16..].contains('2'));
+        assert_eq!(super::wires(&mut rng, 6).lines().map(|line| line.split(',').count()).collect::<Vec<_>>(), [6, 6]);
+
+        let range = super::password_range(&mut rng, 100);
+        let (lo, hi) = range.split_once('-').unwrap();
+
+        assert!(lo.parse::<u64>().unwrap() <= hi.parse::<u64>().unwrap());
+        assert_eq!(super::object_name(0), "0");
+        assert_eq!(super::object_name(36 + 10), "1a");
+    }
+}
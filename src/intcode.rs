@@ -0,0 +1,1006 @@
+//! The Intcode computer shared by days 7, 9 and 11. Parse a `Program`, `spawn` a `Process` from
+//! it, `feed` it input and `read` its output, or hand it an `InputSource` and `OutputSink`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Stdin, Stdout, Write};
+use std::ops::{Index, IndexMut};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender};
+
+use self::object::Metadata;
+use crate::error::{number, parse_fields, ParseError};
+use self::replay::{Event, Journal};
+
+pub mod dap;
+pub mod disasm;
+pub mod dump;
+pub mod gdb;
+pub mod network;
+pub mod object;
+pub mod packet;
+pub mod repl;
+pub mod replay;
+pub mod taint;
+
+/// Supplies values to the `in` instruction. Returning `None` makes the process block.
+pub trait InputSource {
+    fn next_input(&mut self) -> Option<i64>;
+}
+
+/// Receives every value produced by the `out` instruction.
+pub trait OutputSink {
+    fn write_output(&mut self, value: i64);
+
+    /// Whether the sink takes no more values, which stops the process with `OutputClosed`.
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+impl InputSource for VecDeque<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.pop_front()
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    fn write_output(&mut self, value: i64) {
+        self.push_back(value);
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    fn write_output(&mut self, value: i64) {
+        self.push(value);
+    }
+}
+
+impl<F: FnMut() -> Option<i64>> InputSource for F {
+    fn next_input(&mut self) -> Option<i64> {
+        self()
+    }
+}
+
+impl<F: FnMut(i64)> OutputSink for F {
+    fn write_output(&mut self, value: i64) {
+        self(value)
+    }
+}
+
+impl InputSource for Receiver<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.recv().ok()
+    }
+}
+
+impl OutputSink for Sender<i64> {
+    fn write_output(&mut self, value: i64) {
+        // A hung up receiver has no use for further values
+        let _ = self.send(value);
+    }
+}
+
+pub struct Iter<I>(pub I);
+
+impl<I: Iterator<Item = i64>> InputSource for Iter<I> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.0.next()
+    }
+}
+
+/// Reads comma or whitespace separated numbers. Input ends at the first token that is not a
+/// number or read that fails, and `error` tells which it was.
+pub struct NumberReader<R> {
+    reader: R,
+    pending: VecDeque<i64>,
+    error: Option<String>,
+}
+
+impl<R: BufRead> NumberReader<R> {
+    pub fn new(reader: R) -> NumberReader<R> {
+        NumberReader { reader, pending: VecDeque::new(), error: None }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl NumberReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<NumberReader<BufReader<File>>> {
+        Ok(NumberReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl NumberReader<BufReader<Stdin>> {
+    pub fn stdin() -> NumberReader<BufReader<Stdin>> {
+        NumberReader::new(BufReader::new(io::stdin()))
+    }
+}
+
+impl<R: BufRead> InputSource for NumberReader<R> {
+    fn next_input(&mut self) -> Option<i64> {
+        while self.pending.is_empty() && self.error.is_none() {
+            let mut line = String::new();
+
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => self.error = Some(err.to_string()),
+            }
+
+            for token in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()) {
+                match token.parse::<i64>() {
+                    Ok(value) => self.pending.push_back(value),
+                    Err(err) => {
+                        self.error = Some(format!("invalid input value `{}`: {}", token, err));
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+/// Writes one number per line. The first failed write closes the sink and is kept in `error`.
+pub struct NumberWriter<W> {
+    writer: W,
+    ascii: bool,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> NumberWriter<W> {
+    pub fn new(writer: W) -> NumberWriter<W> {
+        NumberWriter { writer, ascii: false, error: None }
+    }
+
+    /// Writes values below 128 as the character they encode instead.
+    pub fn ascii(self) -> NumberWriter<W> {
+        NumberWriter { ascii: true, ..self }
+    }
+
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+}
+
+impl NumberWriter<File> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<NumberWriter<File>> {
+        Ok(NumberWriter::new(File::create(path)?))
+    }
+}
+
+impl NumberWriter<Stdout> {
+    pub fn stdout() -> NumberWriter<Stdout> {
+        NumberWriter::new(io::stdout())
+    }
+}
+
+impl<W: Write> OutputSink for NumberWriter<W> {
+    fn write_output(&mut self, value: i64) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let written = if self.ascii && (0..128).contains(&value) {
+            write!(self.writer, "{}", value as u8 as char)
+        } else {
+            writeln!(self.writer, "{}", value)
+        };
+
+        self.error = written.and_then(|_| self.writer.flush()).err();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+const PAGE_SIZE: usize = 512;
+
+/// How many cells past the memory in use `poke` reaches, so that a wild address typed into a
+/// debugger is turned down instead of allocating pages up to it.
+pub const POKE_REACH: usize = 64 * PAGE_SIZE;
+
+type Page = [i64; PAGE_SIZE];
+
+/// Paged memory shared between forks. A page is only copied when a fork writes to it while
+/// another process still holds it, and pages that were never written are not allocated at all.
+#[derive(Clone)]
+struct Memory {
+    pages: Vec<Option<Arc<Page>>>,
+    len: usize,
+}
+
+impl Memory {
+    fn new(cells: &[i64]) -> Memory {
+        let pages = cells.chunks(PAGE_SIZE).map(|chunk| {
+            let mut page = [0; PAGE_SIZE];
+            page[..chunk.len()].copy_from_slice(chunk);
+            Some(Arc::new(page))
+        }).collect();
+
+        Memory { pages, len: cells.len() }
+    }
+
+    fn to_vec(&self) -> Vec<i64> {
+        (0..self.len).map(|i| self[i]).collect()
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i64;
+
+    fn index(&self, index: usize) -> &i64 {
+        match self.pages.get(index / PAGE_SIZE) {
+            Some(Some(page)) => &page[index % PAGE_SIZE],
+            _ => &0,
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, index: usize) -> &mut i64 {
+        let page = index / PAGE_SIZE;
+
+        if self.pages.len() <= page {
+            self.pages.resize(page + 1, None);
+        }
+
+        self.len = self.len.max(index + 1);
+
+        let page = self.pages[page].get_or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+        &mut Arc::make_mut(page)[index % PAGE_SIZE]
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Parameter {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Parameter {
+    fn new(mode: i64, value: i64) -> Result<Parameter, Fault> {
+        match mode {
+            0 => Ok(Parameter::Position(value)),
+            1 => Ok(Parameter::Immediate(value)),
+            2 => Ok(Parameter::Relative(value)),
+            _ => Err(Fault::InvalidMode(mode)),
+        }
+    }
+
+    fn read(memory: &Memory, eip: usize, offset: usize) -> Result<Parameter, Fault> {
+        let mode = (memory[eip] / 10i64.pow(1 + (offset as u32))) % 10;
+        let value = memory[eip + offset];
+
+        Parameter::new(mode, value)
+    }
+
+    fn load(self, memory: &Memory, rbo: usize) -> Result<i64, Fault> {
+        match self {
+            Parameter::Immediate(value) => Ok(value),
+            _ => Ok(memory[self.address(rbo)?]),
+        }
+    }
+
+    fn address(self, rbo: usize) -> Result<usize, Fault> {
+        let address = match self {
+            Parameter::Position(pos) => pos,
+            Parameter::Immediate(_) => return Err(Fault::WriteToImmediate),
+            Parameter::Relative(offset) => (rbo as i64).wrapping_add(offset),
+        };
+
+        to_address(address)
+    }
+}
+
+fn to_address(value: i64) -> Result<usize, Fault> {
+    usize::try_from(value).map_err(|_| Fault::NegativeAddress(value))
+}
+
+/// A running copy of a program, with its own memory, registers and input and output buffers.
+#[derive(Clone)]
+pub struct Process {
+    memory: Memory,
+    eip: usize,
+    rbo: usize,
+    steps: u64,
+    breakpoints: BTreeSet<usize>,
+    input_buffer: VecDeque<i64>,
+    output_buffer: VecDeque<i64>,
+    journal: Option<Journal>,
+    writers: Option<HashMap<usize, usize>>,
+}
+
+/// Why `run` and friends handed control back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopReason {
+    Halted,
+    WaitingForInput,
+    OutputReady,
+    Breakpoint,
+    BudgetExhausted,
+    MemoryLimit,
+    /// The output sink takes no more values. The `out` instruction that found this out has run.
+    OutputClosed,
+    /// The instruction at eip cannot be executed. Nothing was changed, so it stays at eip.
+    Fault(Fault),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    UnknownOpcode(i64),
+    InvalidMode(i64),
+    WriteToImmediate,
+    NegativeAddress(i64),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::UnknownOpcode(op) => write!(f, "unknown opcode {}", op),
+            Fault::InvalidMode(mode) => write!(f, "invalid parameter mode {}", mode),
+            Fault::WriteToImmediate => write!(f, "write to an immediate parameter"),
+            Fault::NegativeAddress(address) => write!(f, "negative address {}", address),
+        }
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Halted => write!(f, "halted"),
+            StopReason::WaitingForInput => write!(f, "waiting for input"),
+            StopReason::OutputReady => write!(f, "output ready"),
+            StopReason::Breakpoint => write!(f, "breakpoint"),
+            StopReason::BudgetExhausted => write!(f, "instruction budget exhausted"),
+            StopReason::MemoryLimit => write!(f, "memory limit exceeded"),
+            StopReason::OutputClosed => write!(f, "output closed"),
+            StopReason::Fault(fault) => write!(f, "{}", fault),
+        }
+    }
+}
+
+impl From<Fault> for StopReason {
+    fn from(fault: Fault) -> StopReason {
+        StopReason::Fault(fault)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunResult {
+    pub reason: StopReason,
+    pub eip: usize,
+}
+
+/// Extra conditions for `run_until`, on top of halting and blocking on input.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Until {
+    pub outputs: Option<usize>,
+    pub budget: Option<u64>,
+    /// Stops before an instruction that would make the process touch more cells than this.
+    pub memory: Option<usize>,
+}
+
+enum Executed {
+    Instruction,
+    Output,
+    Blocked,
+    Halted,
+}
+
+impl Process {
+    /// Queues a value for the next input instruction.
+    pub fn feed(&mut self, value: i64) {
+        self.input_buffer.push_back(value);
+    }
+
+    /// Takes the oldest output that has not been read yet.
+    pub fn read(&mut self) -> Option<i64> {
+        self.output_buffer.pop_front()
+    }
+
+    pub fn eip(&self) -> usize {
+        self.eip
+    }
+
+    pub fn set_eip(&mut self, eip: usize) {
+        self.eip = eip;
+    }
+
+    pub fn rbo(&self) -> usize {
+        self.rbo
+    }
+
+    pub fn set_rbo(&mut self, rbo: usize) {
+        self.rbo = rbo;
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Copy of the memory touched so far; every cell beyond it reads as zero.
+    pub fn memory(&self) -> Vec<i64> {
+        self.memory.to_vec()
+    }
+
+    pub fn memory_len(&self) -> usize {
+        self.memory.len
+    }
+
+    pub fn peek(&self, address: usize) -> i64 {
+        self.memory[address]
+    }
+
+    /// Writes a cell, refusing addresses more than `POKE_REACH` cells past the memory in use.
+    pub fn poke(&mut self, address: usize, value: i64) -> Result<(), String> {
+        if address >= self.memory.len.saturating_add(POKE_REACH) {
+            return Err(format!("Address {} is out of reach, only {} cells are in use", address, self.memory.len));
+        }
+
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    /// Branches off an independent copy of this process. Memory pages are shared until either
+    /// side writes to them, so forking costs one pointer per page rather than a full copy.
+    pub fn fork(&self) -> Process {
+        self.clone()
+    }
+
+    /// Starts a fresh journal of every value read and written from now on.
+    pub fn record(&mut self) {
+        self.journal = Some(Journal::default());
+    }
+
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    /// Starts remembering which instruction last wrote each cell.
+    pub fn track_writes(&mut self) {
+        self.writers = Some(HashMap::new());
+    }
+
+    /// Address of the instruction that last wrote `address` since `track_writes` was called.
+    pub fn last_writer(&self, address: usize) -> Option<usize> {
+        self.writers.as_ref().and_then(|writers| writers.get(&address).copied())
+    }
+
+    fn wrote(&mut self, address: usize) {
+        if let Some(writers) = &mut self.writers {
+            writers.insert(address, self.eip);
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn has_breakpoint(&self, address: usize) -> bool {
+        self.breakpoints.contains(&address)
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
+    }
+
+    pub fn step(&mut self) -> RunResult {
+        self.run_until(Until { budget: Some(1), ..Until::default() })
+    }
+
+    /// Runs until the program halts or waits for input that has not been fed.
+    pub fn run(&mut self) -> StopReason {
+        self.run_until(Until::default()).reason
+    }
+
+    /// Like `run`, but reading from `input` and writing to `output` instead of the buffers.
+    pub fn run_with<I, O>(&mut self, input: &mut I, output: &mut O) -> StopReason
+    where
+        I: InputSource + ?Sized,
+        O: OutputSink + ?Sized,
+    {
+        self.run_until_with(Until::default(), input, output).reason
+    }
+
+    pub fn run_until(&mut self, until: Until) -> RunResult {
+        let mut input = std::mem::take(&mut self.input_buffer);
+        let mut output = std::mem::take(&mut self.output_buffer);
+
+        let result = self.run_until_with(until, &mut input, &mut output);
+
+        self.input_buffer = input;
+        self.output_buffer = output;
+
+        result
+    }
+
+    pub fn run_until_with<I, O>(&mut self, until: Until, input: &mut I, output: &mut O) -> RunResult
+    where
+        I: InputSource + ?Sized,
+        O: OutputSink + ?Sized,
+    {
+        let mut executed = 0u64;
+        let mut outputs = 0usize;
+
+        let reason = loop {
+            // Resuming from a breakpoint must not trigger it again
+            if executed > 0 && self.breakpoints.contains(&self.eip) {
+                break StopReason::Breakpoint;
+            }
+
+            if until.budget == Some(executed) {
+                break StopReason::BudgetExhausted;
+            }
+
+            match self.execute(until.memory, input, output) {
+                Ok(Executed::Halted) => break StopReason::Halted,
+                Ok(Executed::Blocked) => break StopReason::WaitingForInput,
+                Ok(Executed::Instruction) => {}
+                Ok(Executed::Output) => outputs += 1,
+                Err(reason) => break reason,
+            }
+
+            executed += 1;
+            self.steps += 1;
+
+            if output.is_closed() {
+                break StopReason::OutputClosed;
+            }
+
+            if until.outputs == Some(outputs) {
+                break StopReason::OutputReady;
+            }
+        };
+
+        RunResult { reason, eip: self.eip }
+    }
+
+    /// Where `out` points, checked against the memory limit before anything is written there.
+    fn target(&self, out: Parameter, limit: Option<usize>) -> Result<usize, StopReason> {
+        let address = out.address(self.rbo)?;
+
+        if address >= self.memory.len && limit.is_some_and(|limit| address >= limit) {
+            return Err(StopReason::MemoryLimit);
+        }
+
+        Ok(address)
+    }
+
+    fn store(&mut self, address: usize, value: i64) {
+        self.memory[address] = value;
+        self.wrote(address);
+    }
+
+    fn execute<I, O>(&mut self, limit: Option<usize>, input: &mut I, output: &mut O) -> Result<Executed, StopReason>
+    where
+        I: InputSource + ?Sized,
+        O: OutputSink + ?Sized,
+    {
+        match self.memory[self.eip] % 100 {
+            1 => {
+                let lhs = Parameter::read(&self.memory, self.eip, 1)?;
+                let rhs = Parameter::read(&self.memory, self.eip, 2)?;
+                let out = Parameter::read(&self.memory, self.eip, 3)?;
+                let address = self.target(out, limit)?;
+                self.store(address, lhs.load(&self.memory, self.rbo)?.wrapping_add(rhs.load(&self.memory, self.rbo)?));
+                self.eip += 4;
+            }
+            2 => {
+                let lhs = Parameter::read(&self.memory, self.eip, 1)?;
+                let rhs = Parameter::read(&self.memory, self.eip, 2)?;
+                let out = Parameter::read(&self.memory, self.eip, 3)?;
+                let address = self.target(out, limit)?;
+                self.store(address, lhs.load(&self.memory, self.rbo)?.wrapping_mul(rhs.load(&self.memory, self.rbo)?));
+                self.eip += 4;
+            }
+            3 => {
+                let out = Parameter::read(&self.memory, self.eip, 1)?;
+                let address = self.target(out, limit)?;
+                let value = match input.next_input() {
+                    None => return Ok(Executed::Blocked),
+                    Some(value) => value,
+                };
+
+                if let Some(journal) = &mut self.journal {
+                    journal.events.push(Event::Input { step: self.steps, value });
+                }
+
+                self.store(address, value);
+                self.eip += 2;
+            }
+            4 => {
+                let src = Parameter::read(&self.memory, self.eip, 1)?;
+                let value = src.load(&self.memory, self.rbo)?;
+
+                if let Some(journal) = &mut self.journal {
+                    journal.events.push(Event::Output { step: self.steps, value });
+                }
+
+                output.write_output(value);
+                self.eip += 2;
+                return Ok(Executed::Output);
+            }
+            5 => {
+                let test = Parameter::read(&self.memory, self.eip, 1)?;
+                let jump = Parameter::read(&self.memory, self.eip, 2)?;
+
+                if test.load(&self.memory, self.rbo)? != 0 {
+                    self.eip = to_address(jump.load(&self.memory, self.rbo)?)?;
+                } else {
+                    self.eip += 3;
+                }
+            }
+            6 => {
+                let test = Parameter::read(&self.memory, self.eip, 1)?;
+                let jump = Parameter::read(&self.memory, self.eip, 2)?;
+
+                if test.load(&self.memory, self.rbo)? == 0 {
+                    self.eip = to_address(jump.load(&self.memory, self.rbo)?)?;
+                } else {
+                    self.eip += 3;
+                }
+            }
+            7 => {
+                let lhs = Parameter::read(&self.memory, self.eip, 1)?;
+                let rhs = Parameter::read(&self.memory, self.eip, 2)?;
+                let out = Parameter::read(&self.memory, self.eip, 3)?;
+                let address = self.target(out, limit)?;
+                self.store(address, if lhs.load(&self.memory, self.rbo)? < rhs.load(&self.memory, self.rbo)? { 1 } else { 0 });
+                self.eip += 4;
+            }
+            8 => {
+                let lhs = Parameter::read(&self.memory, self.eip, 1)?;
+                let rhs = Parameter::read(&self.memory, self.eip, 2)?;
+                let out = Parameter::read(&self.memory, self.eip, 3)?;
+                let address = self.target(out, limit)?;
+                self.store(address, if lhs.load(&self.memory, self.rbo)? == rhs.load(&self.memory, self.rbo)? { 1 } else { 0 });
+                self.eip += 4;
+            }
+            9 => {
+                let val = Parameter::read(&self.memory, self.eip, 1)?;
+                self.rbo = to_address((self.rbo as i64).wrapping_add(val.load(&self.memory, self.rbo)?))?;
+                self.eip += 2;
+            }
+            99 => {
+                return Ok(Executed::Halted);
+            }
+            _ => {
+                return Err(Fault::UnknownOpcode(self.memory[self.eip]).into());
+            }
+        }
+
+        Ok(Executed::Instruction)
+    }
+}
+
+/// Intcode that has not started running, along with any metadata from the object format.
+#[derive(Clone, Debug)]
+pub struct Program {
+    code: Vec<i64>,
+    metadata: Metadata,
+}
+
+/// Accepts plain comma separated code as well as the object format from `object`.
+impl FromStr for Program {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with(object::MAGIC) {
+            let (code, metadata) = object::parse(s.trim_start())?;
+            return Ok(Program { code, metadata });
+        }
+
+        parse_fields(s, ',', number).map(Program::new)
+    }
+}
+
+/// Writes plain comma separated code, or the object format when there is metadata to keep.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.metadata.is_empty() {
+            return object::write(f, &self.code, &self.metadata);
+        }
+
+        write!(f, "{}", self.code.iter().map(i64::to_string).collect::<Vec<_>>().join(","))
+    }
+}
+
+impl Program {
+    pub fn new(code: Vec<i64>) -> Program {
+        Program { code, metadata: Metadata::default() }
+    }
+
+    pub fn with_metadata(code: Vec<i64>, metadata: Metadata) -> Program {
+        Program { code, metadata }
+    }
+
+    pub fn code(&self) -> &[i64] {
+        &self.code
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Jump targets found by the disassembler, with declared symbols taking precedence.
+    pub fn labels(&self) -> BTreeMap<usize, String> {
+        let mut labels = disasm::labels(&self.code);
+        labels.extend(self.metadata.symbols.iter().map(|(address, name)| (*address, name.clone())));
+        labels
+    }
+
+    /// A new process at the entry point, with its memory loaded from the code.
+    pub fn spawn(&self) -> Process {
+        Process {
+            memory: Memory::new(&self.code),
+            eip: self.metadata.entry,
+            rbo: 0,
+            steps: 0,
+            breakpoints: BTreeSet::new(),
+            input_buffer: VecDeque::new(),
+            output_buffer: VecDeque::new(),
+            journal: None,
+            writers: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Iter, NumberReader, NumberWriter, Program, StopReason};
+
+    fn run(source: &str, input: Vec<i64>) -> Vec<i64> {
+        let program = source.parse::<Program>().unwrap();
+
+        let mut process = program.spawn();
+        let mut input = input.iter();
+        let mut output = Vec::new();
+
+        loop {
+            match process.run() {
+                StopReason::Halted => break,
+                StopReason::WaitingForInput => process.input_buffer.push_back(*input.next().unwrap()),
+                reason => panic!("Unexpected stop: {:?}", reason),
+            }
+        }
+
+        while let Some(foo) = process.read() {
+            output.push(foo);
+        }
+
+        output
+    }
+
+    #[test]
+    fn day5_part1() {
+        assert_eq!(run("1002,4,3,4,33", vec![]), vec![]);
+        assert_eq!(run("1101,100,-1,4,0", vec![]), vec![]);
+        assert_eq!(run("1002,6,3,6,4,0,33", vec![]), vec![1002]);
+        assert_eq!(run("3,5,4,5,99,0", vec![-16]), vec![-16]);
+    }
+
+    #[test]
+    fn day5_part2() {
+        assert_eq!(run("3,9,8,9,10,9,4,9,99,-1,8", vec![7]), vec![0]);
+        assert_eq!(run("3,9,8,9,10,9,4,9,99,-1,8", vec![8]), vec![1]);
+
+        assert_eq!(run("3,9,7,9,10,9,4,9,99,-1,8", vec![7]), vec![1]);
+        assert_eq!(run("3,9,7,9,10,9,4,9,99,-1,8", vec![8]), vec![0]);
+
+        assert_eq!(run("3,3,1108,-1,8,3,4,3,99", vec![7]), vec![0]);
+        assert_eq!(run("3,3,1108,-1,8,3,4,3,99", vec![8]), vec![1]);
+
+        assert_eq!(run("3,3,1107,-1,8,3,4,3,99", vec![7]), vec![1]);
+        assert_eq!(run("3,3,1107,-1,8,3,4,3,99", vec![8]), vec![0]);
+
+        assert_eq!(run("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", vec![0]), vec![0]);
+        assert_eq!(run("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", vec![2]), vec![1]);
+
+        assert_eq!(run("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", vec![0]), vec![0]);
+        assert_eq!(run("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", vec![2]), vec![1]);
+    }
+
+    #[test]
+    fn day9_part1() {
+        assert_eq!(run("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99", vec![]), vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]);
+        assert_eq!(run("1102,34915192,34915192,7,4,7,99,0", vec![]), vec![34915192 * 34915192]);
+        assert_eq!(run("104,1125899906842624,99", vec![]), vec![1125899906842624]);
+    }
+
+    #[test]
+    fn closures() {
+        let program = "3,9,8,9,10,9,4,9,99,-1,8".parse::<Program>().unwrap();
+        let mut reads = 0;
+        let mut output = Vec::new();
+
+        let result = program.spawn().run_with(&mut || { reads += 1; Some(8) }, &mut |value| output.push(value));
+
+        assert_eq!(result, StopReason::Halted);
+        assert_eq!(reads, 1);
+        assert_eq!(output, vec![1]);
+    }
+
+    #[test]
+    fn iterators() {
+        let program = "3,0,4,0,3,0,4,0,99".parse::<Program>().unwrap();
+        let mut process = program.spawn();
+        let mut output = Vec::new();
+
+        assert_eq!(process.run_with(&mut Iter(vec![5].into_iter()), &mut output), StopReason::WaitingForInput);
+        assert_eq!(process.run_with(&mut Iter(vec![7].into_iter()), &mut output), StopReason::Halted);
+        assert_eq!(output, vec![5, 7]);
+    }
+
+    #[test]
+    fn channels() {
+        use std::sync::mpsc::channel;
+        use std::thread;
+
+        let program = "3,0,1001,0,1,0,4,0,99".parse::<Program>().unwrap();
+        let (in_tx, mut in_rx) = channel();
+        let (mut out_tx, out_rx) = channel();
+
+        let mut process = program.spawn();
+        let handle = thread::spawn(move || process.run_with(&mut in_rx, &mut out_tx));
+
+        in_tx.send(41).unwrap();
+
+        assert_eq!(out_rx.recv(), Ok(42));
+        assert_eq!(handle.join().unwrap(), StopReason::Halted);
+    }
+
+    #[test]
+    fn numbers() {
+        let program = "3,0,3,1,1,0,1,0,4,0,99".parse::<Program>().unwrap();
+        let mut input = NumberReader::new("12,\n 30\n".as_bytes());
+        let mut output = NumberWriter::new(Vec::new());
+
+        assert_eq!(program.spawn().run_with(&mut input, &mut output), StopReason::Halted);
+        assert_eq!(String::from_utf8(output.writer).unwrap(), "42\n");
+
+        let mut input = NumberReader::new("12 x 30\n".as_bytes());
+        let mut output = NumberWriter::new(Vec::new());
+
+        assert_eq!(program.spawn().run_with(&mut input, &mut output), StopReason::WaitingForInput);
+        assert_eq!(input.error(), Some("invalid input value `x`: invalid digit found in string"));
+
+        // Writes the input forever
+        let program = "3,100,4,100,1105,1,2".parse::<Program>().unwrap();
+        let mut buffer = [0u8; 6];
+        let mut output = NumberWriter::new(&mut buffer[..]).ascii();
+        let mut process = program.spawn();
+
+        assert_eq!(process.run_with(&mut Iter(std::iter::once(65)), &mut output), StopReason::OutputClosed);
+        assert_eq!(output.error().map(|err| err.kind()), Some(std::io::ErrorKind::WriteZero));
+        assert_eq!(process.steps(), 1 + 6 * 2 + 1);
+        assert_eq!(&buffer, b"AAAAAA");
+    }
+
+    #[test]
+    fn run_until() {
+        use super::{RunResult, Until};
+
+        let program = "3,9,4,9,4,9,1101,0,0,9,99".parse::<Program>().unwrap();
+        let mut process = program.spawn();
+
+        assert_eq!(process.run_until(Until::default()), RunResult { reason: StopReason::WaitingForInput, eip: 0 });
+
+        process.feed(5);
+
+        assert_eq!(process.run_until(Until { budget: Some(1), ..Until::default() }), RunResult { reason: StopReason::BudgetExhausted, eip: 2 });
+        assert_eq!(process.run_until(Until { outputs: Some(1), ..Until::default() }), RunResult { reason: StopReason::OutputReady, eip: 4 });
+        assert_eq!(process.read(), Some(5));
+
+        process.add_breakpoint(6);
+
+        assert_eq!(process.run_until(Until::default()), RunResult { reason: StopReason::Breakpoint, eip: 6 });
+        assert_eq!(process.read(), Some(5));
+        assert_eq!(process.run_until(Until::default()), RunResult { reason: StopReason::Halted, eip: 10 });
+        assert_eq!(process.run_until(Until::default()), RunResult { reason: StopReason::Halted, eip: 10 });
+        assert_eq!(process.steps(), 4);
+    }
+
+    #[test]
+    fn memory_limit() {
+        use super::{RunResult, Until};
+
+        let program = "1101,1,2,50,1101,3,4,5000,99".parse::<Program>().unwrap();
+        let mut process = program.spawn();
+        let until = Until { memory: Some(100), ..Until::default() };
+
+        assert_eq!(process.run_until(until), RunResult { reason: StopReason::MemoryLimit, eip: 4 });
+        assert_eq!(process.memory_len(), 51);
+
+        let program = "1101,1,2,100000000000000,99".parse::<Program>().unwrap();
+        let mut process = program.spawn();
+
+        assert_eq!(process.run_until(until), RunResult { reason: StopReason::MemoryLimit, eip: 0 });
+        assert_eq!(process.memory_len(), 5);
+        assert_eq!(process.steps(), 0);
+    }
+
+    #[test]
+    fn faults() {
+        use super::{Fault, RunResult};
+
+        let fault = |source: &str| source.parse::<Program>().unwrap().spawn().run_until(Default::default());
+
+        assert_eq!(fault("1,0,0,0,55"), RunResult { reason: StopReason::Fault(Fault::UnknownOpcode(55)), eip: 4 });
+        assert_eq!(fault("301,0,0,0"), RunResult { reason: StopReason::Fault(Fault::InvalidMode(3)), eip: 0 });
+        assert_eq!(fault("11101,1,2,3"), RunResult { reason: StopReason::Fault(Fault::WriteToImmediate), eip: 0 });
+        assert_eq!(fault("1,-3,0,0"), RunResult { reason: StopReason::Fault(Fault::NegativeAddress(-3)), eip: 0 });
+        assert_eq!(fault("109,-1,99"), RunResult { reason: StopReason::Fault(Fault::NegativeAddress(-1)), eip: 0 });
+        assert_eq!(fault("1105,1,-7"), RunResult { reason: StopReason::Fault(Fault::NegativeAddress(-7)), eip: 0 });
+
+        // A faulting input instruction leaves its input queued
+        let mut process = "3,-1,99".parse::<Program>().unwrap().spawn();
+
+        process.feed(4);
+
+        assert_eq!(process.run(), StopReason::Fault(Fault::NegativeAddress(-1)));
+        assert_eq!(process.steps(), 0);
+        assert_eq!(process.input_buffer.len(), 1);
+    }
+
+    #[test]
+    fn fork_copies_on_write() {
+        use std::sync::Arc;
+
+        let program = "1101,1,2,1000,99".parse::<Program>().unwrap();
+        let parent = program.spawn();
+        let mut child = parent.fork();
+
+        assert!(Arc::ptr_eq(parent.memory.pages[0].as_ref().unwrap(), child.memory.pages[0].as_ref().unwrap()));
+        assert_eq!(child.run(), StopReason::Halted);
+
+        // The first page is still shared, the second only exists in the child
+        assert!(Arc::ptr_eq(parent.memory.pages[0].as_ref().unwrap(), child.memory.pages[0].as_ref().unwrap()));
+        assert_eq!(parent.memory.pages.len(), 1);
+        assert_eq!(child.peek(1000), 3);
+        assert_eq!(parent.peek(1000), 0);
+        assert_eq!(child.memory_len(), 1001);
+
+        child.poke(0, 7).unwrap();
+        assert_eq!(parent.peek(0), 1101);
+        assert_eq!(parent.eip(), 0);
+    }
+
+    #[test]
+    fn fork_search() {
+        use std::collections::VecDeque;
+
+        // Reads three bits and outputs the number they spell
+        let program = "3,100,3,101,3,102,1002,100,4,100,1002,101,2,101,1,100,101,100,1,100,102,100,4,100,99".parse::<Program>().unwrap();
+        let mut queue = VecDeque::new();
+        let mut results = Vec::new();
+
+        queue.push_back(program.spawn());
+
+        while let Some(mut process) = queue.pop_front() {
+            match process.run() {
+                StopReason::WaitingForInput => {
+                    for bit in 0..2 {
+                        let mut branch = process.fork();
+                        branch.feed(bit);
+                        queue.push_back(branch);
+                    }
+                }
+                StopReason::Halted => results.push(process.read().unwrap()),
+                reason => panic!("Unexpected stop: {:?}", reason),
+            }
+        }
+
+        assert_eq!(results, (0..8).collect::<Vec<_>>());
+    }
+}
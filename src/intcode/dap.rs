@@ -0,0 +1,747 @@
+//! Debug Adapter Protocol server wrapping a `Process`, speaking JSON over stdio.
+//!
+//! The program is presented as a generated disassembly listing (source reference 1), so editors can
+//! set breakpoints by listing line, by label through function breakpoints, or by address through
+//! instruction breakpoints. Registers and memory show up as variables, and the debug console
+//! accepts `feed 1 2 3` to queue input as well as `eip`, `rbo` and `[n]` to inspect values.
+//!
+//! Launching with a `journal` replays a recorded session: its inputs are queued up front and what
+//! the program does is compared with the journal, reporting the first divergence on the console.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+use super::disasm::{self, Line};
+use super::replay::Journal;
+use super::{Process, Program, StopReason, Until};
+use crate::json::Value;
+use crate::json_object;
+
+const THREAD_ID: i64 = 1;
+const SOURCE_REFERENCE: i64 = 1;
+const REGISTERS_REFERENCE: i64 = 1;
+const MEMORY_REFERENCE: i64 = 2;
+
+/// Instructions a running program executes between looking for requests such as `pause`.
+const SLICE: u64 = 100_000;
+
+/// Largest message body the adapter reads, so a bogus `Content-Length` cannot exhaust memory.
+const MAX_MESSAGE: usize = 1 << 20;
+
+struct Session {
+    name: String,
+    process: Process,
+    lines: Vec<Line>,
+    labels: BTreeMap<usize, String>,
+    stop_on_entry: bool,
+    source_breakpoints: BTreeSet<usize>,
+    function_breakpoints: BTreeSet<usize>,
+    instruction_breakpoints: BTreeSet<usize>,
+    applied_breakpoints: BTreeSet<usize>,
+    running: bool,
+    /// The journal being replayed, until the replay diverges from it or finishes.
+    replay: Option<Journal>,
+}
+
+type Event = (&'static str, Value);
+
+impl Session {
+    fn launch(arguments: &Value) -> Result<Session, String> {
+        let path = arguments.get("program").and_then(Value::as_str).ok_or("Missing program path")?;
+        let source = fs::read_to_string(path).map_err(|err| format!("Cannot read {}: {}", path, err))?;
+        let program = source.trim().parse::<Program>().map_err(|err| format!("Invalid program {}: {}", path, err))?;
+
+        let mut process = program.spawn();
+
+        for value in arguments.get("input").and_then(Value::as_array).unwrap_or(&[]) {
+            process.feed(value.as_i64().ok_or("Input values must be integers")?);
+        }
+
+        let replay = match arguments.get("journal").map(|path| path.as_str().ok_or("Journal path must be a string")) {
+            Some(path) => {
+                let path = path?;
+                let text = fs::read_to_string(path).map_err(|err| format!("Cannot read {}: {}", path, err))?;
+                let journal = text.parse::<Journal>().map_err(|err| format!("Invalid journal {}: {}", path, err))?;
+
+                for value in journal.inputs() {
+                    process.feed(value);
+                }
+
+                process.record();
+                Some(journal)
+            }
+            None => None,
+        };
+
+        let labels = program.labels();
+        let lines = disasm::listing(program.code(), &labels);
+        let name = Path::new(path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| path.to_string());
+
+        Ok(Session {
+            name,
+            process,
+            lines,
+            labels,
+            stop_on_entry: arguments.get("stopOnEntry").and_then(Value::as_bool).unwrap_or(false),
+            source_breakpoints: BTreeSet::new(),
+            function_breakpoints: BTreeSet::new(),
+            instruction_breakpoints: BTreeSet::new(),
+            applied_breakpoints: BTreeSet::new(),
+            running: false,
+            replay,
+        })
+    }
+
+    fn source(&self) -> Value {
+        json_object! {
+            "name" => format!("{} (disassembly)", self.name),
+            "sourceReference" => SOURCE_REFERENCE,
+        }
+    }
+
+    fn line_of(&self, address: usize) -> usize {
+        self.lines.iter().rposition(|line| line.address <= address).unwrap_or(0) + 1
+    }
+
+    fn address_of(&self, name: &str) -> Option<usize> {
+        match self.labels.iter().find(|(_, label)| *label == name) {
+            Some((address, _)) => Some(*address),
+            None => name.parse().ok(),
+        }
+    }
+
+    fn apply_breakpoints(&mut self) {
+        for address in &self.applied_breakpoints {
+            self.process.remove_breakpoint(*address);
+        }
+
+        self.applied_breakpoints = &(&self.source_breakpoints | &self.function_breakpoints) | &self.instruction_breakpoints;
+
+        for address in &self.applied_breakpoints {
+            self.process.add_breakpoint(*address);
+        }
+    }
+
+    fn verified(&self, address: Option<usize>) -> Value {
+        match address {
+            Some(address) => json_object! {
+                "verified" => true,
+                "line" => self.line_of(address),
+                "source" => self.source(),
+                "instructionReference" => address.to_string(),
+            },
+            None => json_object! { "verified" => false },
+        }
+    }
+
+    /// Executes a single instruction, or with `step` unset lets the program run until `slice`
+    /// sees it stop.
+    fn resume(&mut self, step: bool) -> Vec<Event> {
+        if !step {
+            self.running = true;
+            return Vec::new();
+        }
+
+        let reason = self.process.step().reason;
+        self.stopped(Some(reason))
+    }
+
+    /// Runs a little further, reporting outputs and, once the program stopped, why.
+    fn slice(&mut self) -> Vec<Event> {
+        let reason = self.process.run_until(Until { budget: Some(SLICE), ..Until::default() }).reason;
+
+        if reason == StopReason::BudgetExhausted {
+            return self.stopped(None);
+        }
+
+        self.running = false;
+        self.stopped(Some(reason))
+    }
+
+    /// Output written so far followed by the events for `reason`, if the program stopped at all.
+    fn stopped(&mut self, reason: Option<StopReason>) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut output = String::new();
+
+        while let Some(value) = self.process.read() {
+            output.push_str(&format!("{}\n", value));
+        }
+
+        if !output.is_empty() {
+            events.push(("output", json_object! { "category" => "stdout", "output" => output }));
+        }
+
+        if let Some(message) = self.check_replay(reason == Some(StopReason::Halted)) {
+            events.push(("output", json_object! { "category" => "console", "output" => message }));
+        }
+
+        let reason = match reason {
+            Some(reason) => reason,
+            None => return events,
+        };
+
+        match reason {
+            StopReason::Halted => {
+                events.push(("exited", json_object! { "exitCode" => 0 }));
+                events.push(("terminated", json_object! {}));
+            }
+            StopReason::WaitingForInput => events.push(stopped("pause", Some("Waiting for input"))),
+            StopReason::Breakpoint => events.push(stopped("breakpoint", None)),
+            StopReason::OutputReady | StopReason::BudgetExhausted => events.push(stopped("step", None)),
+            StopReason::MemoryLimit => events.push(stopped("exception", Some("Memory limit exceeded"))),
+            StopReason::OutputClosed => events.push(stopped("exception", Some("Output closed"))),
+            StopReason::Fault(fault) => events.push(stopped("exception", Some(&fault.to_string()))),
+        }
+
+        events
+    }
+
+    /// What to tell the user about the replay, once it diverges or the program `finished`.
+    fn check_replay(&mut self, finished: bool) -> Option<String> {
+        let journal = self.replay.as_ref()?;
+        let actual = self.process.journal().map_or(&[][..], |j| &j.events[..]);
+
+        let message = match journal.divergence(actual, 0, finished) {
+            Some(divergence) => format!("{}\n", divergence),
+            None if finished => format!("Replayed all {} events of the journal\n", journal.events.len()),
+            None => return None,
+        };
+
+        self.replay = None;
+        Some(message)
+    }
+
+    fn stack_trace(&self) -> Value {
+        let eip = self.process.eip();
+        let name = self.labels.range(..=eip).next_back().map(|(_, label)| label.as_str()).unwrap_or("start");
+
+        json_object! {
+            "stackFrames" => vec![json_object! {
+                "id" => 0,
+                "name" => name,
+                "source" => self.source(),
+                "line" => self.line_of(eip),
+                "column" => 1,
+                "instructionPointerReference" => eip.to_string(),
+            }],
+            "totalFrames" => 1,
+        }
+    }
+
+    fn variables(&self, arguments: &Value) -> Result<Value, String> {
+        let variable = |name: String, value: i64| json_object! { "name" => name, "value" => value.to_string(), "variablesReference" => 0 };
+
+        let variables = match arguments.get("variablesReference").and_then(Value::as_i64) {
+            Some(REGISTERS_REFERENCE) => vec![
+                variable("eip".to_string(), self.process.eip() as i64),
+                variable("rbo".to_string(), self.process.rbo() as i64),
+                variable("steps".to_string(), self.process.steps() as i64),
+            ],
+            Some(MEMORY_REFERENCE) => {
+                let len = self.process.memory_len();
+                let start = index(arguments.get("start"), 0)?;
+                let count = index(arguments.get("count"), len)?;
+
+                // Cells past the memory in use all read as zero, so they are not worth listing
+                (start..start.saturating_add(count).min(len)).map(|i| variable(format!("[{}]", i), self.process.peek(i))).collect()
+            }
+            _ => return Err("Unknown variables reference".to_string()),
+        };
+
+        Ok(json_object! { "variables" => variables })
+    }
+
+    fn set_variable(&mut self, arguments: &Value) -> Result<Value, String> {
+        let name = arguments.get("name").and_then(Value::as_str).ok_or("Missing variable name")?;
+        let value = arguments.get("value").and_then(Value::as_str).and_then(|v| v.trim().parse::<i64>().ok()).ok_or("Value must be an integer")?;
+
+        match (arguments.get("variablesReference").and_then(Value::as_i64), name) {
+            (Some(REGISTERS_REFERENCE), "eip") => self.process.set_eip(usize::try_from(value).map_err(|_| "eip cannot be negative")?),
+            (Some(REGISTERS_REFERENCE), "rbo") => self.process.set_rbo(usize::try_from(value).map_err(|_| "rbo cannot be negative")?),
+            (Some(MEMORY_REFERENCE), name) => {
+                let address = name.trim_start_matches('[').trim_end_matches(']').parse::<usize>().map_err(|_| "Invalid memory cell")?;
+                self.process.poke(address, value)?;
+            }
+            _ => return Err(format!("Cannot set {}", name)),
+        }
+
+        Ok(json_object! { "value" => value.to_string() })
+    }
+
+    fn evaluate(&mut self, expression: &str) -> Result<String, String> {
+        let mut words = expression.split_whitespace();
+
+        match words.next() {
+            Some("feed") => {
+                let values = words.map(|w| w.parse::<i64>()).collect::<Result<Vec<_>, _>>().map_err(|_| "usage: feed <numbers>")?;
+
+                for value in &values {
+                    self.process.feed(*value);
+                }
+
+                Ok(format!("queued {} input(s)", values.len()))
+            }
+            Some("eip") => Ok(self.process.eip().to_string()),
+            Some("rbo") => Ok(self.process.rbo().to_string()),
+            Some(cell) if cell.starts_with('[') && cell.ends_with(']') => {
+                let target = &cell[1..cell.len() - 1];
+                let address = self.address_of(target).ok_or_else(|| format!("Unknown address {}", target))?;
+
+                Ok(self.process.peek(address).to_string())
+            }
+            _ => Err(format!("Cannot evaluate {}", expression)),
+        }
+    }
+}
+
+/// An optional non-negative integer argument.
+fn index(argument: Option<&Value>, default: usize) -> Result<usize, String> {
+    match argument {
+        None => Ok(default),
+        Some(value) => value.as_i64().and_then(|value| usize::try_from(value).ok()).ok_or_else(|| format!("Expected a non-negative integer, got {}", value)),
+    }
+}
+
+fn stopped(reason: &str, description: Option<&str>) -> Event {
+    let mut body = json_object! { "reason" => reason, "threadId" => THREAD_ID, "allThreadsStopped" => true };
+
+    if let Some(description) = description {
+        body.insert("description", description);
+    }
+
+    ("stopped", body)
+}
+
+pub struct Adapter {
+    seq: i64,
+    session: Option<Session>,
+    finished: bool,
+}
+
+impl Default for Adapter {
+    fn default() -> Adapter {
+        Adapter::new()
+    }
+}
+
+impl Adapter {
+    pub fn new() -> Adapter {
+        Adapter { seq: 0, session: None, finished: false }
+    }
+
+    /// Set once the client has disconnected and no further messages are expected.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether the program was continued and has not stopped yet, so `poll` should be called
+    /// whenever there is no request to handle.
+    pub fn is_running(&self) -> bool {
+        self.session.as_ref().is_some_and(|session| session.running)
+    }
+
+    /// Lets a running program run a little further and returns the events that produced.
+    pub fn poll(&mut self) -> Vec<Value> {
+        let events = match &mut self.session {
+            Some(session) if session.running => session.slice(),
+            _ => Vec::new(),
+        };
+
+        events.into_iter().map(|event| self.event(event)).collect()
+    }
+
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let command = message.get("command").and_then(Value::as_str).unwrap_or("");
+        let empty = Value::Object(Vec::new());
+        let arguments = message.get("arguments").unwrap_or(&empty);
+
+        let mut response = json_object! {
+            "seq" => self.next_seq(),
+            "type" => "response",
+            "request_seq" => message.get("seq").cloned().unwrap_or(Value::Null),
+            "command" => command,
+        };
+
+        let mut messages = Vec::new();
+
+        match self.dispatch(command, arguments) {
+            Ok((body, events)) => {
+                response.insert("success", true);
+
+                if body != Value::Null {
+                    response.insert("body", body);
+                }
+
+                messages.push(response);
+
+                for event in events {
+                    let event = self.event(event);
+                    messages.push(event);
+                }
+            }
+            Err(message) => {
+                response.insert("success", false);
+                response.insert("message", message);
+                messages.push(response);
+            }
+        }
+
+        messages
+    }
+
+    /// Tells the client about a message that could not be taken as a request at all.
+    pub fn reject(&mut self, reason: &str) -> Vec<Value> {
+        vec![self.event(("output", json_object! { "category" => "stderr", "output" => format!("{}\n", reason) }))]
+    }
+
+    fn event(&mut self, (event, body): Event) -> Value {
+        json_object! { "seq" => self.next_seq(), "type" => "event", "event" => event, "body" => body }
+    }
+
+    fn next_seq(&mut self) -> i64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn session(&mut self) -> Result<&mut Session, String> {
+        self.session.as_mut().ok_or_else(|| "No program has been launched".to_string())
+    }
+
+    fn dispatch(&mut self, command: &str, arguments: &Value) -> Result<(Value, Vec<Event>), String> {
+        let done = |body: Value| Ok((body, Vec::new()));
+
+        match command {
+            "initialize" => Ok((
+                json_object! {
+                    "supportsConfigurationDoneRequest" => true,
+                    "supportsFunctionBreakpoints" => true,
+                    "supportsInstructionBreakpoints" => true,
+                    "supportsSetVariable" => true,
+                },
+                vec![("initialized", json_object! {})],
+            )),
+            "launch" => {
+                self.session = Some(Session::launch(arguments)?);
+                done(Value::Null)
+            }
+            "setBreakpoints" => {
+                let session = self.session()?;
+                let requested = arguments.get("breakpoints").and_then(Value::as_array).unwrap_or(&[]);
+                let addresses = requested.iter()
+                    .map(|bp| bp.get("line").and_then(Value::as_i64).and_then(|line| session.lines.get((line as usize).wrapping_sub(1))).map(|line| line.address))
+                    .collect::<Vec<_>>();
+
+                session.source_breakpoints = addresses.iter().flatten().copied().collect();
+                session.apply_breakpoints();
+
+                done(json_object! { "breakpoints" => addresses.into_iter().map(|a| session.verified(a)).collect::<Vec<_>>() })
+            }
+            "setFunctionBreakpoints" => {
+                let session = self.session()?;
+                let requested = arguments.get("breakpoints").and_then(Value::as_array).unwrap_or(&[]);
+                let addresses = requested.iter()
+                    .map(|bp| bp.get("name").and_then(Value::as_str).and_then(|name| session.address_of(name)))
+                    .collect::<Vec<_>>();
+
+                session.function_breakpoints = addresses.iter().flatten().copied().collect();
+                session.apply_breakpoints();
+
+                done(json_object! { "breakpoints" => addresses.into_iter().map(|a| session.verified(a)).collect::<Vec<_>>() })
+            }
+            "setInstructionBreakpoints" => {
+                let session = self.session()?;
+                let requested = arguments.get("breakpoints").and_then(Value::as_array).unwrap_or(&[]);
+                let addresses = requested.iter()
+                    .map(|bp| {
+                        let base = bp.get("instructionReference").and_then(Value::as_str).and_then(|r| r.parse::<i64>().ok())?;
+                        let address = base.checked_add(bp.get("offset").and_then(Value::as_i64).unwrap_or(0))?;
+
+                        usize::try_from(address).ok()
+                    })
+                    .collect::<Vec<_>>();
+
+                session.instruction_breakpoints = addresses.iter().flatten().copied().collect();
+                session.apply_breakpoints();
+
+                done(json_object! { "breakpoints" => addresses.into_iter().map(|a| session.verified(a)).collect::<Vec<_>>() })
+            }
+            "configurationDone" => {
+                let session = self.session()?;
+
+                if session.stop_on_entry {
+                    Ok((Value::Null, vec![stopped("entry", None)]))
+                } else {
+                    Ok((Value::Null, session.resume(false)))
+                }
+            }
+            "threads" => done(json_object! { "threads" => vec![json_object! { "id" => THREAD_ID, "name" => "intcode" }] }),
+            "stackTrace" => done(self.session()?.stack_trace()),
+            "scopes" => {
+                let session = self.session()?;
+
+                done(json_object! {
+                    "scopes" => vec![
+                        json_object! { "name" => "Registers", "variablesReference" => REGISTERS_REFERENCE, "expensive" => false },
+                        json_object! { "name" => "Memory", "variablesReference" => MEMORY_REFERENCE, "indexedVariables" => session.process.memory_len(), "expensive" => true },
+                    ],
+                })
+            }
+            "variables" => done(self.session()?.variables(arguments)?),
+            "setVariable" => done(self.session()?.set_variable(arguments)?),
+            "source" => {
+                let session = self.session()?;
+                let content = session.lines.iter().map(|line| line.to_string() + "\n").collect::<String>();
+
+                done(json_object! { "content" => content, "mimeType" => "text/x-intcode" })
+            }
+            "evaluate" => {
+                let expression = arguments.get("expression").and_then(Value::as_str).unwrap_or("");
+                let result = self.session()?.evaluate(expression)?;
+
+                done(json_object! { "result" => result, "variablesReference" => 0 })
+            }
+            "continue" => Ok((json_object! { "allThreadsContinued" => true }, self.session()?.resume(false))),
+            "next" | "stepIn" => Ok((Value::Null, self.session()?.resume(true))),
+            "pause" => {
+                let session = self.session()?;
+
+                // Stopping a program that is not running would confuse the client about its state
+                if !session.running {
+                    return done(Value::Null);
+                }
+
+                session.running = false;
+
+                let mut events = session.stopped(None);
+                events.push(stopped("pause", None));
+
+                Ok((Value::Null, events))
+            }
+            "terminate" => {
+                self.session = None;
+                Ok((Value::Null, vec![("terminated", json_object! {})]))
+            }
+            "disconnect" => {
+                self.session = None;
+                self.finished = true;
+                done(Value::Null)
+            }
+            _ => Err(format!("Unsupported command: {}", command)),
+        }
+    }
+}
+
+/// Reads the next message. One that cannot be used but was framed correctly comes back as the
+/// reason why, so the messages after it can still be read.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Result<Value, String>>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header"))?;
+
+    if length > MAX_MESSAGE {
+        io::copy(&mut io::Read::take(&mut *reader, length as u64), &mut io::sink())?;
+        return Ok(Some(Err(format!("Ignored a message of {} bytes, the limit is {}", length, MAX_MESSAGE))));
+    }
+
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+
+    let message = String::from_utf8(body)
+        .map_err(|err| err.to_string())
+        .and_then(|body| body.parse::<Value>().map_err(|err| err.to_string()))
+        .map_err(|err| format!("Ignored a message that is not JSON: {}", err));
+
+    Ok(Some(message))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Reads messages on a thread of its own, so requests can arrive while the program runs.
+fn spawn_reader<R: BufRead + Send + 'static>(mut reader: R) -> Receiver<io::Result<Result<Value, String>>> {
+    let (sender, receiver) = channel();
+
+    thread::spawn(move || {
+        loop {
+            let message = match read_message(&mut reader) {
+                Ok(Some(message)) => Ok(message),
+                Ok(None) => break,
+                Err(err) => Err(err),
+            };
+            let failed = message.is_err();
+
+            if sender.send(message).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+pub fn serve<R: BufRead + Send + 'static, W: Write>(reader: R, mut writer: W) -> io::Result<()> {
+    let mut adapter = Adapter::new();
+    let messages = spawn_reader(reader);
+
+    loop {
+        let message = if adapter.is_running() {
+            match messages.try_recv() {
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match messages.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            }
+        };
+
+        let replies = match message {
+            Some(message) => match message? {
+                Ok(message) => adapter.handle(&message),
+                Err(reason) => adapter.reject(&reason),
+            },
+            None => adapter.poll(),
+        };
+
+        for reply in replies {
+            write_message(&mut writer, &reply)?;
+        }
+
+        if adapter.is_finished() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::Adapter;
+    use crate::json::Value;
+
+    fn replay(transcript: &str) {
+        let mut adapter = Adapter::new();
+        let mut pending = Vec::new();
+
+        for line in transcript.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            if let Some(request) = line.strip_prefix("->") {
+                assert!(pending.is_empty(), "unanswered messages: {:?}", pending);
+                pending = adapter.handle(&request.parse::<Value>().unwrap());
+
+                while adapter.is_running() {
+                    pending.extend(adapter.poll());
+                }
+
+                pending.reverse();
+            } else if let Some(expected) = line.strip_prefix("<-") {
+                assert_eq!(pending.pop().map(|m| m.to_string()), Some(expected.trim().parse::<Value>().unwrap().to_string()));
+            } else {
+                panic!("Invalid transcript line: {}", line);
+            }
+        }
+
+        assert!(pending.is_empty(), "unanswered messages: {:?}", pending);
+    }
+
+    #[test]
+    fn countdown() {
+        replay(include_str!("transcripts/countdown.dap"));
+    }
+
+    #[test]
+    fn input() {
+        replay(include_str!("transcripts/input.dap"));
+    }
+
+    #[test]
+    fn journal() {
+        replay(include_str!("transcripts/replay.dap"));
+    }
+
+    #[test]
+    fn faults() {
+        replay(include_str!("transcripts/faults.dap"));
+    }
+
+    #[test]
+    fn pause() {
+        let mut adapter = Adapter::new();
+        let mut request = |command: &str, arguments: &str| {
+            let messages = adapter.handle(&format!(r#"{{"seq":1,"type":"request","command":"{}","arguments":{}}}"#, command, arguments).parse::<Value>().unwrap());
+            messages.iter().map(|m| m.get("event").or_else(|| m.get("success")).unwrap().to_string()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(request("launch", r#"{"program":"src/intcode/transcripts/spin.txt"}"#), ["true"]);
+        assert_eq!(request("pause", "{}"), ["true"]);
+        assert_eq!(request("configurationDone", "{}"), ["true"]);
+        assert!(adapter.is_running());
+        assert!(adapter.poll().is_empty());
+        assert!(adapter.is_running());
+
+        let messages = adapter.handle(&r#"{"seq":2,"type":"request","command":"pause"}"#.parse::<Value>().unwrap());
+
+        assert!(!adapter.is_running());
+        assert_eq!(messages[1].to_string(), r#"{"seq":5,"type":"event","event":"stopped","body":{"reason":"pause","threadId":1,"allThreadsStopped":true}}"#);
+    }
+
+    #[test]
+    fn framing() {
+        let request = r#"{"seq":1,"type":"request","command":"disconnect"}"#;
+        let input = format!("Content-Length: {}\r\n\r\n{}", request.len(), request);
+        let mut output = Vec::new();
+
+        super::serve(io::Cursor::new(input), &mut output).unwrap();
+
+        let reply = r#"{"seq":1,"type":"response","request_seq":1,"command":"disconnect","success":true}"#;
+        assert_eq!(String::from_utf8(output).unwrap(), format!("Content-Length: {}\r\n\r\n{}", reply.len(), reply));
+
+        // Unusable messages are reported and skipped, and a huge one is not read into memory
+        let input = format!("Content-Length: 3\r\n\r\nabcContent-Length: {}\r\n\r\n{}Content-Length: 99999999999\r\n\r\n", request.len(), request);
+        let mut output = Vec::new();
+
+        super::serve(io::Cursor::new(input), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(r#""category":"stderr","output":"Ignored a message that is not JSON: "#));
+        assert!(output.contains(r#""command":"disconnect","success":true"#));
+
+        let input = "Content-Length: 99999999999\r\n\r\n{}";
+        let mut output = Vec::new();
+
+        super::serve(io::Cursor::new(input), &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains(r#""output":"Ignored a message of 99999999999 bytes, the limit is 1048576\n""#));
+    }
+}
+
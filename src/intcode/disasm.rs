@@ -0,0 +1,397 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::Process;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Operand {
+    pub mode: Mode,
+    pub value: i64,
+}
+
+impl Mode {
+    pub fn code(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Operand, String> {
+        let s = s.trim();
+        let invalid = || format!("Invalid operand `{}`", s);
+
+        let (mode, value) = match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            Some(inner) => match inner.trim().strip_prefix("rb") {
+                Some("") => (Mode::Relative, "0"),
+                Some(offset) => (Mode::Relative, offset.trim().strip_prefix('+').unwrap_or(offset).trim()),
+                None => (Mode::Position, inner.trim()),
+            },
+            None => (Mode::Immediate, s),
+        };
+
+        let value = match value.strip_prefix('-') {
+            Some(digits) => digits.trim().parse::<i64>().map(|v| -v),
+            None => value.parse::<i64>(),
+        };
+
+        Ok(Operand { mode, value: value.map_err(|_| invalid())? })
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Immediate => write!(f, "{}", self.value),
+            Mode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            Mode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opcode {
+    Add,
+    Mul,
+    In,
+    Out,
+    Jnz,
+    Jz,
+    Lt,
+    Eq,
+    Arb,
+    Hlt,
+}
+
+impl Opcode {
+    pub const ALL: [Opcode; 10] = [Opcode::Add, Opcode::Mul, Opcode::In, Opcode::Out, Opcode::Jnz, Opcode::Jz, Opcode::Lt, Opcode::Eq, Opcode::Arb, Opcode::Hlt];
+
+    pub fn from_code(code: i64) -> Option<Opcode> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Mul),
+            3 => Some(Opcode::In),
+            4 => Some(Opcode::Out),
+            5 => Some(Opcode::Jnz),
+            6 => Some(Opcode::Jz),
+            7 => Some(Opcode::Lt),
+            8 => Some(Opcode::Eq),
+            9 => Some(Opcode::Arb),
+            99 => Some(Opcode::Hlt),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::In => 3,
+            Opcode::Out => 4,
+            Opcode::Jnz => 5,
+            Opcode::Jz => 6,
+            Opcode::Lt => 7,
+            Opcode::Eq => 8,
+            Opcode::Arb => 9,
+            Opcode::Hlt => 99,
+        }
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+            Opcode::Jnz | Opcode::Jz => 2,
+            Opcode::In | Opcode::Out | Opcode::Arb => 1,
+            Opcode::Hlt => 0,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Mul => "mul",
+            Opcode::In => "in",
+            Opcode::Out => "out",
+            Opcode::Jnz => "jnz",
+            Opcode::Jz => "jz",
+            Opcode::Lt => "lt",
+            Opcode::Eq => "eq",
+            Opcode::Arb => "arb",
+            Opcode::Hlt => "hlt",
+        }
+    }
+
+    /// Index of the operand that gets written to, if any.
+    pub fn output_operand(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => Some(2),
+            Opcode::In => Some(0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Number of cells the instruction occupies, opcode included.
+    pub fn size(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    /// Cells that make up the instruction, with the parameter modes folded into the opcode.
+    pub fn encode(&self) -> Vec<i64> {
+        let modes = self.operands.iter().rev().fold(0, |modes, operand| modes * 10 + operand.mode.code());
+        let mut cells = vec![modes * 100 + self.opcode.code()];
+
+        cells.extend(self.operands.iter().map(|operand| operand.value));
+        cells
+    }
+
+    /// Target of a jump whose destination is known without running the program.
+    pub fn static_target(&self) -> Option<usize> {
+        match (self.opcode, self.operands.get(1)) {
+            (Opcode::Jnz, Some(target)) | (Opcode::Jz, Some(target)) if target.mode == Mode::Immediate && target.value >= 0 => Some(target.value as usize),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the syntax produced by `Display`, such as `add [100], -1, [rb+3]`.
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Instruction, String> {
+        let s = s.trim();
+        let (mnemonic, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+
+        let opcode = Opcode::ALL.iter().copied()
+            .find(|opcode| opcode.mnemonic() == mnemonic)
+            .ok_or_else(|| format!("Unknown mnemonic `{}`", mnemonic))?;
+
+        let operands = match rest.trim() {
+            "" => Vec::new(),
+            rest => rest.split(',').map(str::parse::<Operand>).collect::<Result<Vec<_>, _>>()?,
+        };
+
+        if operands.len() != opcode.arity() {
+            return Err(format!("`{}` takes {} operand(s), found {}", mnemonic, opcode.arity(), operands.len()));
+        }
+
+        if let Some(output) = opcode.output_operand() {
+            if operands[output].mode == Mode::Immediate {
+                return Err(format!("`{}` cannot write to an immediate operand", mnemonic));
+            }
+        }
+
+        Ok(Instruction { opcode, operands })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode.mnemonic())?;
+
+        for (i, operand) in self.operands.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Cell-by-cell view of memory, so code can be decoded from an image or a running process alike.
+pub trait Image {
+    fn cell(&self, address: usize) -> i64;
+}
+
+impl Image for [i64] {
+    fn cell(&self, address: usize) -> i64 {
+        self.get(address).copied().unwrap_or(0)
+    }
+}
+
+impl<const N: usize> Image for [i64; N] {
+    fn cell(&self, address: usize) -> i64 {
+        self[..].cell(address)
+    }
+}
+
+impl Image for Process {
+    fn cell(&self, address: usize) -> i64 {
+        self.peek(address)
+    }
+}
+
+/// Decodes the instruction at `address`, or `None` if the cell does not hold a valid one.
+pub fn decode<M: Image + ?Sized>(memory: &M, address: usize) -> Option<Instruction> {
+    let cell = |index: usize| memory.cell(index);
+    let raw = cell(address);
+
+    if raw < 0 {
+        return None;
+    }
+
+    let opcode = Opcode::from_code(raw % 100)?;
+    let mut modes = raw / 100;
+    let mut operands = Vec::with_capacity(opcode.arity());
+
+    for i in 0..opcode.arity() {
+        let mode = match modes % 10 {
+            0 => Mode::Position,
+            1 => Mode::Immediate,
+            2 => Mode::Relative,
+            _ => return None,
+        };
+
+        if mode == Mode::Immediate && opcode.output_operand() == Some(i) {
+            return None;
+        }
+
+        operands.push(Operand { mode, value: cell(address + 1 + i) });
+        modes /= 10;
+    }
+
+    if modes != 0 {
+        return None;
+    }
+
+    Some(Instruction { opcode, operands })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub address: usize,
+    pub size: usize,
+    pub label: Option<String>,
+    pub text: String,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = self.label.as_ref().map(|l| format!("{}:", l)).unwrap_or_default();
+
+        write!(f, "{:>5}  {:<8}{}", self.address, label, self.text)
+    }
+}
+
+/// Names every statically known jump target so listings and debuggers can refer to it.
+pub fn labels(memory: &[i64]) -> BTreeMap<usize, String> {
+    let mut labels = BTreeMap::new();
+    let mut address = 0;
+
+    while address < memory.len() {
+        match decode(memory, address) {
+            Some(instruction) => {
+                if let Some(target) = instruction.static_target() {
+                    labels.insert(target, format!("L{}", target));
+                }
+
+                address += instruction.size();
+            }
+            None => address += 1,
+        }
+    }
+
+    labels
+}
+
+/// Linear sweep over the whole image. Cells that do not decode are shown as `data`.
+pub fn listing(memory: &[i64], labels: &BTreeMap<usize, String>) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < memory.len() {
+        let label = labels.get(&address).cloned();
+
+        let (size, text) = match decode(memory, address).filter(|i| address + i.size() <= memory.len()) {
+            Some(instruction) => {
+                let text = match instruction.static_target().and_then(|target| labels.get(&target)) {
+                    Some(name) => format!("{} {}, {}", instruction.opcode.mnemonic(), instruction.operands[0], name),
+                    None => instruction.to_string(),
+                };
+
+                (instruction.size(), text)
+            }
+            None => (1, format!("data {}", memory[address])),
+        };
+
+        lines.push(Line { address, size, label, text });
+        address += size;
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, labels, listing};
+
+    #[test]
+    fn instructions() {
+        let memory = [1002, 4, 3, 4, 33, 21101, -1, 7, 3, 109, 19, 99];
+
+        assert_eq!(decode(&memory, 0).unwrap().to_string(), "mul [4], 3, [4]");
+        assert_eq!(decode(&memory, 5).unwrap().to_string(), "add -1, 7, [rb+3]");
+        assert_eq!(decode(&memory, 9).unwrap().to_string(), "arb 19");
+        assert_eq!(decode(&memory, 11).unwrap().to_string(), "hlt");
+        assert_eq!(decode(&memory, 4), None);
+        assert_eq!(decode(&[11101, 1, 2, 3], 0), None);
+    }
+
+    #[test]
+    fn assembly() {
+        use super::Instruction;
+
+        let memory = [1002, 4, 3, 4, 21101, -1, 7, 3, 204, -2, 99];
+        let mut address = 0;
+
+        while address < memory.len() {
+            let instruction = decode(&memory, address).unwrap();
+            let parsed = instruction.to_string().parse::<Instruction>().unwrap();
+
+            assert_eq!(parsed.encode(), &memory[address..address + instruction.size()]);
+            address += instruction.size();
+        }
+
+        assert_eq!("out [ rb ]".parse::<Instruction>().map(|i| i.encode()), Ok(vec![204, 0]));
+        assert!("add 1, 2".parse::<Instruction>().is_err());
+        assert!("add 1, 2, 3".parse::<Instruction>().is_err());
+        assert!("jmp 4".parse::<Instruction>().is_err());
+        assert!("out [x]".parse::<Instruction>().is_err());
+    }
+
+    #[test]
+    fn listings() {
+        let memory = [3, 100, 4, 100, 1001, 100, -1, 100, 1005, 100, 2, 99, 7];
+        let labels = labels(&memory);
+        let lines = listing(&memory, &labels).iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(lines, vec![
+            "    0          in [100]",
+            "    2  L2:     out [100]",
+            "    4          add [100], -1, [100]",
+            "    8          jnz [100], L2",
+            "   11          hlt",
+            "   12          data 7",
+        ]);
+    }
+}
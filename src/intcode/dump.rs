@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::disasm::{self, Image};
+use super::Process;
+
+/// Memory laid out one instruction per row, raw cells on the left and their decoding on the right.
+/// Runs of zero cells that do not decode are collapsed into a single `*` row, as `hexdump` does.
+pub fn dump(memory: &[i64], labels: &BTreeMap<usize, String>) -> String {
+    let width = memory.iter().map(|cell| cell.to_string().len()).max().unwrap_or(1);
+    let mut result = String::new();
+    let mut previous_zero = false;
+    let mut collapsed = false;
+
+    for line in disasm::listing(memory, labels) {
+        let zero = line.label.is_none() && line.text == "data 0";
+
+        if zero && previous_zero {
+            if !collapsed {
+                result.push_str("    *\n");
+                collapsed = true;
+            }
+
+            continue;
+        }
+
+        previous_zero = zero;
+        collapsed = false;
+
+        let cells = (0..4)
+            .map(|i| if i < line.size { format!("{:>width$}", memory[line.address + i], width = width) } else { " ".repeat(width) })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let label = line.label.map(|l| format!("{}:", l)).unwrap_or_default();
+
+        result.push_str(&format!("{:>5}  {}  {:<8}{}\n", line.address, cells, label, line.text));
+    }
+
+    result
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Change {
+    pub address: usize,
+    pub before: i64,
+    pub after: i64,
+    pub writer: Option<usize>,
+}
+
+/// Cells of `after` that differ from `before`, usually the `Program` image it was spawned from.
+/// Writers are only known when the process was tracking writes.
+pub fn diff(before: &[i64], after: &Process) -> Vec<Change> {
+    (0..before.len().max(after.memory_len()))
+        .map(|address| Change { address, before: before.cell(address), after: after.peek(address), writer: after.last_writer(address) })
+        .filter(|change| change.before != change.after)
+        .collect()
+}
+
+/// Shows a diff with the instruction that last wrote each cell, decoded from the current memory.
+pub struct Diff<'a> {
+    pub changes: &'a [Change],
+    pub process: &'a Process,
+}
+
+impl fmt::Display for Diff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in self.changes {
+            write!(f, "{:>5}  {} -> {}", change.address, change.before, change.after)?;
+
+            if let Some(writer) = change.writer {
+                let instruction = disasm::decode(self.process, writer).map(|i| i.to_string()).unwrap_or_else(|| "?".to_string());
+                write!(f, "  by {}: {}", writer, instruction)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, dump, Change, Diff};
+    use crate::intcode::disasm::labels;
+    use crate::intcode::{Program, StopReason};
+
+    #[test]
+    fn dumps() {
+        let memory = [3, 9, 1005, 9, 8, 0, 0, 0, 99, 0, 0, 0];
+
+        assert_eq!(dump(&memory, &labels(&memory)), [
+            "    0     3    9                    in [9]",
+            "    2  1005    9    8               jnz [9], L8",
+            "    5     0                         data 0",
+            "    *",
+            "    8    99                 L8:     hlt",
+            "    9     0                         data 0",
+            "    *",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn self_modifying() {
+        // The day5 example that patches its own halt instruction
+        let program = "1002,4,3,4,33".parse::<Program>().unwrap();
+        let mut process = program.spawn();
+
+        process.track_writes();
+        assert_eq!(process.run(), StopReason::Halted);
+
+        let changes = diff(program.code(), &process);
+
+        assert_eq!(changes, vec![Change { address: 4, before: 33, after: 99, writer: Some(0) }]);
+        assert_eq!(Diff { changes: &changes, process: &process }.to_string(), "    4  33 -> 99  by 0: mul [4], 3, [4]\n");
+    }
+}
@@ -0,0 +1,627 @@
+//! GDB remote serial protocol stub exposing a `Process` as a debug target.
+//!
+//! GDB thinks in bytes while Intcode memory is made of `i64` cells, so every cell is presented as
+//! eight little endian bytes: cell `n` lives at byte address `8 * n`. The `eip` and `rbo` registers
+//! are presented in the same byte address space, which keeps `break *16` and `x/2gd $rbo` working.
+//! Outputs are forwarded as console output and inputs can be queued with `monitor feed 1 2 3`.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use super::{Fault, Process, StopReason, Until};
+
+const CELL_SIZE: usize = 8;
+
+/// Largest packet we accept or send, announced to the debugger in hex.
+const PACKET_SIZE: usize = 0x4000;
+
+/// Instructions `c` runs before it looks for an interrupt from the debugger.
+const SLICE: u64 = 100_000;
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+const SIGTTIN: u8 = 21;
+
+const INTERRUPT: u8 = 0x03;
+
+const TARGET_XML: &str = concat!(
+    r#"<?xml version="1.0"?><!DOCTYPE target SYSTEM "gdb-target.dtd">"#,
+    r#"<target><feature name="org.adventofcode.intcode">"#,
+    r#"<reg name="eip" bitsize="64" type="code_ptr" regnum="0"/>"#,
+    r#"<reg name="rbo" bitsize="64" type="data_ptr" regnum="1"/>"#,
+    r#"</feature></target>"#,
+);
+
+enum Reply {
+    Packet(String),
+    Stop(String),
+    Close(Option<String>),
+}
+
+struct Session<'a, W> {
+    process: &'a mut Process,
+    bytes: Receiver<u8>,
+    unread: VecDeque<u8>,
+    writer: W,
+    ack: bool,
+    last_stop: String,
+}
+
+/// Accepts a single debugger connection on `addr` and serves it until it detaches.
+pub fn listen<A: ToSocketAddrs>(process: &mut Process, addr: A) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let (stream, _) = listener.accept()?;
+
+    // Packets are tiny and strictly request/response, so batching only adds latency
+    stream.set_nodelay(true)?;
+
+    serve(process, stream.try_clone()?, stream)
+}
+
+/// Serves a debugger on standard input and output, for `target remote | intcode gdb ...`.
+pub fn stdio(process: &mut Process) -> io::Result<()> {
+    serve(process, io::stdin(), io::stdout())
+}
+
+/// Reads on a thread of its own, so the session can look for interrupts while the process runs.
+fn spawn_reader<R: Read + Send + 'static>(mut reader: R) -> Receiver<u8> {
+    let (sender, receiver) = channel();
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 1024];
+
+        while let Ok(count) = reader.read(&mut buffer) {
+            if count == 0 || buffer[..count].iter().any(|byte| sender.send(*byte).is_err()) {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+pub fn serve<R, W>(process: &mut Process, reader: R, writer: W) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+{
+    let bytes = spawn_reader(reader);
+    let mut session = Session { process, bytes, unread: VecDeque::new(), writer, ack: true, last_stop: format!("S{:02x}", SIGTRAP) };
+
+    while let Some(packet) = session.receive()? {
+        match session.handle(&packet) {
+            Reply::Packet(reply) => session.send(&reply)?,
+            Reply::Stop(reply) => {
+                session.last_stop = reply.clone();
+                session.send(&reply)?;
+            }
+            Reply::Close(reply) => {
+                if let Some(reply) = reply {
+                    session.send(&reply)?;
+                }
+
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn checksum(data: &str) -> u8 {
+    data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte))
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    usize::from_str_radix(s, 16).ok()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut out, byte| {
+        let _ = write!(out, "{:02x}", byte);
+        out
+    })
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn decode_register(s: &str) -> Option<usize> {
+    let bytes = decode_hex(s)?;
+
+    if bytes.len() != CELL_SIZE {
+        return None;
+    }
+
+    let mut raw = [0u8; CELL_SIZE];
+    raw.copy_from_slice(&bytes);
+
+    Some(u64::from_le_bytes(raw) as usize / CELL_SIZE)
+}
+
+impl<'a, W: Write> Session<'a, W> {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.unread.pop_front().or_else(|| self.bytes.recv().ok()))
+    }
+
+    /// Whether the debugger sent an interrupt, keeping anything else it sent for later.
+    fn interrupted(&mut self) -> bool {
+        let mut interrupted = false;
+
+        while let Ok(byte) = self.bytes.try_recv() {
+            if byte == INTERRUPT {
+                interrupted = true;
+            } else {
+                self.unread.push_back(byte);
+            }
+        }
+
+        interrupted
+    }
+
+    fn receive(&mut self) -> io::Result<Option<String>> {
+        loop {
+            // Skip acknowledgements and interrupts until the start of a packet
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                Some(_) => continue,
+            }
+
+            let mut data = Vec::new();
+
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+
+            let mut sum = [0u8; 2];
+
+            for digit in &mut sum {
+                *digit = self.read_byte()?.ok_or(io::ErrorKind::UnexpectedEof)?;
+            }
+
+            let data = String::from_utf8_lossy(&data).into_owned();
+            let valid = std::str::from_utf8(&sum).ok().and_then(|sum| u8::from_str_radix(sum, 16).ok()) == Some(checksum(&data));
+
+            if self.ack {
+                self.writer.write_all(if valid { b"+" } else { b"-" })?;
+                self.writer.flush()?;
+            }
+
+            if valid {
+                return Ok(Some(data));
+            }
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        loop {
+            write!(self.writer, "${}#{:02x}", data, checksum(data))?;
+            self.writer.flush()?;
+
+            if !self.ack {
+                return Ok(());
+            }
+
+            match self.read_byte()? {
+                Some(b'-') => continue,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn handle(&mut self, packet: &str) -> Reply {
+        let reply = |s: &str| Reply::Packet(s.to_string());
+        let error = || Reply::Packet("E01".to_string());
+
+        match packet.as_bytes().first() {
+            Some(b'?') => Reply::Packet(self.last_stop.clone()),
+            Some(b'g') => Reply::Packet(self.registers()),
+            Some(b'G') => match self.write_registers(&packet[1..]) {
+                Some(()) => reply("OK"),
+                None => error(),
+            },
+            Some(b'p') => match parse_hex(&packet[1..]).and_then(|n| self.register(n)) {
+                Some(value) => Reply::Packet(encode_hex(&(value.wrapping_mul(CELL_SIZE) as u64).to_le_bytes())),
+                None => error(),
+            },
+            Some(b'P') => match self.write_register(&packet[1..]) {
+                Some(()) => reply("OK"),
+                None => error(),
+            },
+            Some(b'm') => match self.read_memory(&packet[1..]) {
+                Some(data) => Reply::Packet(data),
+                None => error(),
+            },
+            Some(b'M') => match self.write_memory(&packet[1..]) {
+                Some(()) => reply("OK"),
+                None => error(),
+            },
+            Some(b'Z') | Some(b'z') => match self.breakpoint(packet) {
+                Some(true) => reply("OK"),
+                Some(false) => reply(""),
+                None => error(),
+            },
+            Some(b's') => {
+                if let Some(addr) = parse_hex(&packet[1..]) {
+                    self.process.set_eip(addr / CELL_SIZE);
+                }
+
+                let reason = self.process.step().reason;
+                self.stopped(Some(reason))
+            }
+            Some(b'c') => {
+                if let Some(addr) = parse_hex(&packet[1..]) {
+                    self.process.set_eip(addr / CELL_SIZE);
+                }
+
+                let reason = self.resume();
+                self.stopped(reason)
+            }
+            Some(b'H') => reply("OK"),
+            Some(b'k') => Reply::Close(None),
+            Some(b'D') => Reply::Close(Some("OK".to_string())),
+            _ => self.query(packet),
+        }
+    }
+
+    fn query(&mut self, packet: &str) -> Reply {
+        let reply = |s: &str| Reply::Packet(s.to_string());
+
+        if packet.starts_with("qSupported") {
+            Reply::Packet(format!("PacketSize={:x};qXfer:features:read+;QStartNoAckMode+", PACKET_SIZE))
+        } else if packet == "QStartNoAckMode" {
+            self.ack = false;
+            reply("OK")
+        } else if let Some(annex) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let mut range = annex.split(',').map(parse_hex);
+
+            match (range.next().flatten(), range.next().flatten()) {
+                (Some(offset), Some(length)) if offset <= TARGET_XML.len() => {
+                    let end = offset.saturating_add(length).min(TARGET_XML.len());
+                    let prefix = if end == TARGET_XML.len() { "l" } else { "m" };
+
+                    Reply::Packet(format!("{}{}", prefix, &TARGET_XML[offset..end]))
+                }
+                _ => reply("E00"),
+            }
+        } else if let Some(command) = packet.strip_prefix("qRcmd,") {
+            match decode_hex(command).map(|raw| String::from_utf8_lossy(&raw).into_owned()) {
+                Some(command) => self.monitor(&command),
+                None => reply("E01"),
+            }
+        } else {
+            match packet {
+                "qAttached" => reply("1"),
+                "qC" => reply("QC1"),
+                "qfThreadInfo" => reply("m1"),
+                "qsThreadInfo" => reply("l"),
+                _ => reply(""),
+            }
+        }
+    }
+
+    fn monitor(&mut self, command: &str) -> Reply {
+        let mut words = command.split_whitespace();
+
+        match words.next() {
+            Some("feed") => {
+                match words.map(|w| w.parse::<i64>()).collect::<Result<Vec<_>, _>>() {
+                    Ok(values) => {
+                        for value in values {
+                            self.process.feed(value);
+                        }
+
+                        Reply::Packet("OK".to_string())
+                    }
+                    Err(_) => Reply::Packet(encode_hex(b"usage: monitor feed <numbers>\n")),
+                }
+            }
+            _ => Reply::Packet(encode_hex(b"commands: feed <numbers>\n")),
+        }
+    }
+
+    /// Runs until the process stops by itself, or returns `None` when the debugger interrupts it.
+    fn resume(&mut self) -> Option<StopReason> {
+        loop {
+            let reason = self.process.run_until(Until { budget: Some(SLICE), ..Until::default() }).reason;
+
+            if reason != StopReason::BudgetExhausted {
+                return Some(reason);
+            }
+
+            if self.interrupted() {
+                return None;
+            }
+        }
+    }
+
+    /// Sends pending output and makes the stop reply, where `None` stands for an interrupt.
+    fn stopped(&mut self, reason: Option<StopReason>) -> Reply {
+        let mut console = String::new();
+
+        while let Some(value) = self.process.read() {
+            let _ = writeln!(console, "{}", value);
+        }
+
+        // Console output has to go out before the stop reply
+        if !console.is_empty() && self.send(&format!("O{}", encode_hex(console.as_bytes()))).is_err() {
+            return Reply::Close(None);
+        }
+
+        match reason {
+            Some(StopReason::Halted) => Reply::Stop("W00".to_string()),
+            Some(StopReason::WaitingForInput) => Reply::Stop(format!("S{:02x}", SIGTTIN)),
+            Some(StopReason::Fault(Fault::UnknownOpcode(_))) | Some(StopReason::Fault(Fault::InvalidMode(_))) => Reply::Stop(format!("S{:02x}", SIGILL)),
+            Some(StopReason::Fault(_)) | Some(StopReason::MemoryLimit) => Reply::Stop(format!("S{:02x}", SIGSEGV)),
+            Some(_) => Reply::Stop(format!("S{:02x}", SIGTRAP)),
+            None => Reply::Stop(format!("S{:02x}", SIGINT)),
+        }
+    }
+
+    fn register(&self, n: usize) -> Option<usize> {
+        match n {
+            0 => Some(self.process.eip()),
+            1 => Some(self.process.rbo()),
+            _ => None,
+        }
+    }
+
+    fn registers(&self) -> String {
+        let eip = (self.process.eip().wrapping_mul(CELL_SIZE) as u64).to_le_bytes();
+        let rbo = (self.process.rbo().wrapping_mul(CELL_SIZE) as u64).to_le_bytes();
+
+        encode_hex(&eip) + &encode_hex(&rbo)
+    }
+
+    fn write_registers(&mut self, data: &str) -> Option<()> {
+        if data.len() != 4 * CELL_SIZE {
+            return None;
+        }
+
+        let eip = decode_register(&data[..2 * CELL_SIZE])?;
+        let rbo = decode_register(&data[2 * CELL_SIZE..])?;
+
+        self.process.set_eip(eip);
+        self.process.set_rbo(rbo);
+
+        Some(())
+    }
+
+    fn write_register(&mut self, data: &str) -> Option<()> {
+        let mut parts = data.splitn(2, '=');
+        let n = parse_hex(parts.next()?)?;
+        let value = decode_register(parts.next()?)?;
+
+        match n {
+            0 => self.process.set_eip(value),
+            1 => self.process.set_rbo(value),
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    /// Reads are cut short to what fits in a packet, which the debugger handles by asking again.
+    fn read_memory(&self, data: &str) -> Option<String> {
+        let mut parts = data.splitn(2, ',');
+        let addr = parse_hex(parts.next()?)?;
+        let length = parse_hex(parts.next()?)?.min(PACKET_SIZE / 2);
+        let end = addr.checked_add(length)?;
+
+        let bytes = (addr..end)
+            .map(|byte| self.process.peek(byte / CELL_SIZE).to_le_bytes()[byte % CELL_SIZE])
+            .collect::<Vec<_>>();
+
+        Some(encode_hex(&bytes))
+    }
+
+    fn write_memory(&mut self, data: &str) -> Option<()> {
+        let mut parts = data.splitn(2, ':');
+        let mut range = parts.next()?.splitn(2, ',');
+        let addr = parse_hex(range.next()?)?;
+        let length = parse_hex(range.next()?)?;
+        let bytes = decode_hex(parts.next()?)?;
+
+        if bytes.len() != length || addr.checked_add(length).is_none() {
+            return None;
+        }
+
+        // Touching the last cell first turns down a range that is out of reach before any of it changes
+        if let Some(last) = (addr + length).checked_sub(1).map(|byte| byte / CELL_SIZE) {
+            self.process.poke(last, self.process.peek(last)).ok()?;
+        }
+
+        for (i, byte) in bytes.into_iter().enumerate() {
+            let cell = (addr + i) / CELL_SIZE;
+            let mut raw = self.process.peek(cell).to_le_bytes();
+
+            raw[(addr + i) % CELL_SIZE] = byte;
+            self.process.poke(cell, i64::from_le_bytes(raw)).ok()?;
+        }
+
+        Some(())
+    }
+
+    /// Returns `Some(false)` for breakpoint kinds other than software breakpoints.
+    fn breakpoint(&mut self, packet: &str) -> Option<bool> {
+        let mut parts = packet[1..].split(',');
+        let kind = parts.next()?;
+        let addr = parse_hex(parts.next()?)?;
+
+        if kind != "0" {
+            return Some(false);
+        }
+
+        if packet.starts_with('Z') {
+            self.process.add_breakpoint(addr / CELL_SIZE);
+        } else {
+            self.process.remove_breakpoint(addr / CELL_SIZE);
+        }
+
+        Some(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use super::{checksum, encode_hex};
+    use crate::intcode::Program;
+
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn read_packet(&mut self) -> String {
+            let mut data = Vec::new();
+            let mut byte = [0u8];
+
+            loop {
+                self.stream.read_exact(&mut byte).unwrap();
+                if byte[0] == b'$' { break; }
+            }
+
+            loop {
+                self.stream.read_exact(&mut byte).unwrap();
+                if byte[0] == b'#' { break; }
+                data.push(byte[0]);
+            }
+
+            let mut sum = [0u8; 2];
+            self.stream.read_exact(&mut sum).unwrap();
+            self.stream.write_all(b"+").unwrap();
+
+            let data = String::from_utf8(data).unwrap();
+            assert_eq!(u8::from_str_radix(std::str::from_utf8(&sum).unwrap(), 16).unwrap(), checksum(&data));
+            data
+        }
+
+        fn send(&mut self, data: &str) -> String {
+            write!(self.stream, "${}#{:02x}", data, checksum(data)).unwrap();
+
+            let mut ack = [0u8];
+            self.stream.read_exact(&mut ack).unwrap();
+            assert_eq!(ack[0], b'+');
+
+            self.read_packet()
+        }
+    }
+
+    fn connect(source: &str, input: Vec<i64>) -> (Client, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut process = source.parse::<Program>().unwrap().spawn();
+
+        for value in input {
+            process.feed(value);
+        }
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream.set_nodelay(true).unwrap();
+            super::serve(&mut process, stream.try_clone().unwrap(), stream).unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+
+        (Client { stream }, handle)
+    }
+
+    #[test]
+    fn session() {
+        let (mut client, handle) = connect("3,11,1001,11,1,11,4,11,1105,1,0,0", vec![41]);
+
+        assert!(client.send("qSupported:multiprocess+").contains("qXfer:features:read+"));
+        assert!(client.send("qXfer:features:read:target.xml:0,fff").starts_with("l<?xml"));
+        assert_eq!(client.send("?"), "S05");
+        assert_eq!(client.send("g"), "00000000000000000000000000000000");
+
+        // Cell 2 holds 1001
+        assert_eq!(client.send("m10,8"), "e903000000000000");
+
+        assert_eq!(client.send("Z0,30,1"), "OK");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("p0"), "3000000000000000");
+        assert_eq!(client.send("m58,8"), "2a00000000000000");
+
+        // Console output arrives ahead of the stop reply
+        assert_eq!(client.send("s"), format!("O{}", encode_hex(b"42\n")));
+        assert_eq!(client.read_packet(), "S05");
+        assert_eq!(client.send("p0"), "4000000000000000");
+
+        assert_eq!(client.send("z0,30,1"), "OK");
+        assert_eq!(client.send("c"), "S15");
+        assert_eq!(client.send(&format!("qRcmd,{}", encode_hex(b"feed 99"))), "OK");
+        assert_eq!(client.send("M58,8:0100000000000000"), "OK");
+        assert_eq!(client.send("Z0,30,1"), "OK");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("m58,8"), "6400000000000000");
+
+        assert_eq!(client.send("P0=0000000000000000"), "OK");
+        assert_eq!(client.send("D"), "OK");
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn halting() {
+        let (mut client, handle) = connect("104,7,99", vec![]);
+
+        assert_eq!(client.send("QStartNoAckMode"), "OK");
+
+        write!(client.stream, "$c#63").unwrap();
+        assert_eq!(client.read_packet(), format!("O{}", encode_hex(b"7\n")));
+        assert_eq!(client.read_packet(), "W00");
+
+        write!(client.stream, "$k#6b").unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn interrupts_and_faults() {
+        // Loops until interrupted, then runs into an unknown opcode once the jump is patched out
+        let (mut client, handle) = connect("1105,1,0,55", vec![]);
+
+        write!(client.stream, "$c#63").unwrap();
+        client.stream.write_all(&[0x03]).unwrap();
+
+        assert_eq!(client.read_packet(), "S02");
+        assert_eq!(client.send("m0,8"), "5104000000000000");
+        assert_eq!(client.send("M0,8:0600000000000000"), "OK");
+        assert_eq!(client.send("P0=0000000000000000"), "OK");
+        assert_eq!(client.send("c"), "S04");
+        assert_eq!(client.send("p0"), "1800000000000000");
+
+        // Reads are clamped to a packet and must not wrap around the address space
+        assert_eq!(client.send("m0,ffffffffffffffff").len(), super::PACKET_SIZE);
+        assert_eq!(client.send("mffffffffffffff00,100"), "E01");
+
+        // Writes far past the memory in use are refused instead of allocating up to them
+        assert_eq!(client.send("M7fffffffffff0000,8:0100000000000000"), "E01");
+        assert_eq!(client.send("M8,10:01000000000000000100000000000000"), "OK");
+
+        assert_eq!(client.send("D"), "OK");
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+//! Networks of processes whose outputs feed each other's inputs.
+//!
+//! Each node keeps everything it has written, and every value is also delivered to all of its
+//! targets. A node with several targets copies each value to all of them, and a node with several
+//! sources reads values in the order they arrive.
+
+use super::{Process, StopReason};
+
+struct Node {
+    process: Process,
+    targets: Vec<usize>,
+    outputs: Vec<i64>,
+    halted: bool,
+}
+
+#[derive(Default)]
+pub struct Network {
+    nodes: Vec<Node>,
+}
+
+impl Network {
+    pub fn new() -> Network {
+        Network::default()
+    }
+
+    /// Adds a node and returns the id used to connect, feed and read it.
+    pub fn add(&mut self, process: Process) -> usize {
+        self.nodes.push(Node { process, targets: Vec::new(), outputs: Vec::new(), halted: false });
+        self.nodes.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn feed(&mut self, node: usize, value: i64) {
+        self.nodes[node].process.feed(value);
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.nodes[from].targets.push(to);
+    }
+
+    /// Connects every node to the next one.
+    pub fn chain(&mut self, nodes: &[usize]) {
+        for pair in nodes.windows(2) {
+            self.connect(pair[0], pair[1]);
+        }
+    }
+
+    /// Connects every node to the next one and the last back to the first.
+    pub fn ring(&mut self, nodes: &[usize]) {
+        self.chain(nodes);
+
+        if let (Some(&first), Some(&last)) = (nodes.first(), nodes.last()) {
+            self.connect(last, first);
+        }
+    }
+
+    /// Everything `node` has written so far, including values delivered to its targets.
+    pub fn outputs(&self, node: usize) -> &[i64] {
+        &self.nodes[node].outputs
+    }
+
+    pub fn process(&self, node: usize) -> &Process {
+        &self.nodes[node].process
+    }
+
+    /// Runs the nodes in turn until all of them halted, which returns `Halted`, or until none can
+    /// make progress because the remaining ones wait for input nobody will send, which returns
+    /// `WaitingForInput`. A node that faults stops the whole network with its fault.
+    pub fn run(&mut self) -> StopReason {
+        loop {
+            let mut progress = false;
+
+            for id in 0..self.nodes.len() {
+                let node = &mut self.nodes[id];
+
+                if node.halted {
+                    continue;
+                }
+
+                let steps = node.process.steps();
+                let reason = node.process.run();
+
+                if let StopReason::Fault(_) = reason {
+                    return reason;
+                }
+
+                node.halted = reason == StopReason::Halted;
+                progress |= node.process.steps() > steps || node.halted;
+
+                let produced = std::iter::from_fn(|| node.process.read()).collect::<Vec<_>>();
+                let targets = node.targets.clone();
+
+                node.outputs.extend(&produced);
+
+                for target in targets {
+                    for value in &produced {
+                        self.nodes[target].process.feed(*value);
+                    }
+                }
+            }
+
+            if self.nodes.iter().all(|node| node.halted) {
+                return StopReason::Halted;
+            }
+
+            if !progress {
+                return StopReason::WaitingForInput;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Network;
+    use crate::intcode::{Program, StopReason};
+
+    // Adds its two inputs
+    const ADD: &str = "3,100,3,101,1,100,101,100,4,100,99";
+    // Doubles every input until it reads a zero, which it passes on before halting
+    const DOUBLE: &str = "3,100,1002,100,2,100,4,100,1005,100,0,99";
+
+    #[test]
+    fn fan_out_and_in() {
+        let add = ADD.parse::<Program>().unwrap();
+        let double = DOUBLE.parse::<Program>().unwrap();
+
+        let mut network = Network::new();
+        let source = network.add(double.spawn());
+        let left = network.add(double.spawn());
+        let right = network.add(double.spawn());
+        let sink = network.add(add.spawn());
+
+        network.connect(source, left);
+        network.connect(source, right);
+        network.connect(left, sink);
+        network.connect(right, sink);
+
+        network.feed(source, 3);
+
+        assert_eq!(network.run(), StopReason::WaitingForInput);
+        assert_eq!(network.outputs(source), &[6]);
+        assert_eq!(network.outputs(sink), &[24]);
+
+        network.feed(source, 0);
+
+        assert_eq!(network.run(), StopReason::Halted);
+        assert_eq!(network.outputs(left), &[12, 0]);
+    }
+
+    #[test]
+    fn long_chain() {
+        let double = DOUBLE.parse::<Program>().unwrap();
+        let mut network = Network::new();
+        let nodes = (0..50).map(|_| network.add(double.spawn())).collect::<Vec<_>>();
+
+        network.chain(&nodes);
+        network.feed(nodes[0], 1);
+        network.feed(nodes[0], 0);
+
+        assert_eq!(network.run(), StopReason::Halted);
+        assert_eq!(network.len(), 50);
+        assert_eq!(network.outputs(nodes[49]), &[1 << 50, 0]);
+    }
+}
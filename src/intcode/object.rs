@@ -0,0 +1,228 @@
+//! Text container for Intcode images that carries metadata next to the code.
+//!
+//! ```text
+//! #intcode
+//! entry 0
+//! memory 4096
+//! input count
+//! output value
+//! symbol 2 loop
+//! symbol 100 counter
+//! source 2 countdown.asm:3
+//! code 3,100,4,100,1001,100,-1,100
+//! code 1005,100,2,99
+//! ```
+//!
+//! The first line identifies the format. Every other line is a directive or a `#` comment, and
+//! `code` lines are concatenated in order.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::{column_of, ErrorKind, ParseError};
+
+pub const MAGIC: &str = "#intcode";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub entry: usize,
+    pub memory_limit: Option<usize>,
+    /// Names of the values the program reads, in the order it reads them.
+    pub inputs: Vec<String>,
+    /// Names of the values the program writes, in the order it writes them.
+    pub outputs: Vec<String>,
+    pub symbols: BTreeMap<usize, String>,
+    pub source_map: BTreeMap<usize, SourceLocation>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+}
+
+fn error(line: usize, column: usize, message: String) -> ParseError {
+    ParseError::new(ErrorKind::Invalid(message)).at(line, column)
+}
+
+/// Parses an argument of the directive on `text`, the line it is on. Missing ones are reported at
+/// the end of that line.
+fn number<T: std::str::FromStr>(line: usize, text: &str, value: Option<&str>, what: &'static str) -> Result<T, ParseError> {
+    let value = value.ok_or_else(|| ParseError::new(ErrorKind::Missing(what)).at(line, text.chars().count() + 1))?;
+    value.parse::<T>().map_err(|_| error(line, column_of(text, value), format!("invalid {} `{}`", what, value)))
+}
+
+fn names(rest: &str) -> Vec<String> {
+    rest.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string).collect()
+}
+
+pub fn parse(s: &str) -> Result<(Vec<i64>, Metadata), ParseError> {
+    let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    match lines.next() {
+        Some((_, first)) if first.trim() == MAGIC => {}
+        _ => return Err(error(1, 1, format!("expected `{}`", MAGIC))),
+    }
+
+    let mut code = Vec::new();
+    let mut metadata = Metadata::default();
+
+    for (n, text) in lines {
+        let line = text.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let mut args = rest.split_whitespace();
+
+        match directive {
+            "code" => {
+                for value in rest.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+                    code.push(crate::error::number(value).map_err(|err| err.at(n, column_of(text, value)))?);
+                }
+            }
+            "entry" => metadata.entry = number(n, text, args.next(), "entry point")?,
+            "memory" => metadata.memory_limit = Some(number(n, text, args.next(), "memory limit")?),
+            "input" => metadata.inputs.extend(names(rest)),
+            "output" => metadata.outputs.extend(names(rest)),
+            "symbol" => {
+                let address = number(n, text, args.next(), "address")?;
+                let name = args.next().ok_or_else(|| ParseError::new(ErrorKind::Missing("symbol name")).at(n, text.chars().count() + 1))?;
+
+                metadata.symbols.insert(address, name.to_string());
+            }
+            "source" => {
+                let address = number(n, text, args.next(), "address")?;
+                let location = args.next().ok_or_else(|| ParseError::new(ErrorKind::Missing("source location")).at(n, text.chars().count() + 1))?;
+                let (file, line) = location.rsplit_once(':').ok_or_else(|| error(n, column_of(text, location), format!("expected file:line, found `{}`", location)))?;
+
+                metadata.source_map.insert(address, SourceLocation { file: file.to_string(), line: number(n, text, Some(line), "source line")? });
+            }
+            _ => return Err(error(n, column_of(text, directive), format!("unknown directive `{}`", directive))),
+        }
+    }
+
+    Ok((code, metadata))
+}
+
+pub fn write(f: &mut fmt::Formatter<'_>, code: &[i64], metadata: &Metadata) -> fmt::Result {
+    writeln!(f, "{}", MAGIC)?;
+
+    if metadata.entry != 0 {
+        writeln!(f, "entry {}", metadata.entry)?;
+    }
+
+    if let Some(limit) = metadata.memory_limit {
+        writeln!(f, "memory {}", limit)?;
+    }
+
+    if !metadata.inputs.is_empty() {
+        writeln!(f, "input {}", metadata.inputs.join(", "))?;
+    }
+
+    if !metadata.outputs.is_empty() {
+        writeln!(f, "output {}", metadata.outputs.join(", "))?;
+    }
+
+    for (address, name) in &metadata.symbols {
+        writeln!(f, "symbol {} {}", address, name)?;
+    }
+
+    for (address, location) in &metadata.source_map {
+        writeln!(f, "source {} {}", address, location)?;
+    }
+
+    for chunk in code.chunks(16) {
+        writeln!(f, "code {}", chunk.iter().map(i64::to_string).collect::<Vec<_>>().join(","))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::SourceLocation;
+    use crate::error::ErrorKind;
+    use crate::intcode::{Program, StopReason};
+
+    const COUNTDOWN: &str = "\
+#intcode
+# Counts down from its input
+entry 2
+memory 128
+input count
+output value
+symbol 2 loop
+symbol 100 counter
+source 2 countdown.asm:3
+code 3,100,4,100,1001,100,-1,100
+code 1005,100,2,99
+";
+
+    #[test]
+    fn metadata() {
+        let program = COUNTDOWN.parse::<Program>().unwrap();
+        let metadata = program.metadata();
+
+        assert_eq!(program.code(), &[3, 100, 4, 100, 1001, 100, -1, 100, 1005, 100, 2, 99]);
+        assert_eq!(metadata.entry, 2);
+        assert_eq!(metadata.memory_limit, Some(128));
+        assert_eq!(metadata.inputs, vec!["count"]);
+        assert_eq!(metadata.symbols.get(&100).map(String::as_str), Some("counter"));
+        assert_eq!(metadata.source_map.get(&2), Some(&SourceLocation { file: "countdown.asm".to_string(), line: 3 }));
+        assert_eq!(program.labels().get(&2).map(String::as_str), Some("loop"));
+
+        // Starting at the entry point skips the `in`
+        let mut process = program.spawn();
+        process.poke(100, 2).unwrap();
+
+        assert_eq!(process.run(), StopReason::Halted);
+        assert_eq!(process.read(), Some(2));
+        assert_eq!(process.read(), Some(1));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let program = COUNTDOWN.parse::<Program>().unwrap();
+        let written = program.to_string();
+
+        assert!(written.starts_with("#intcode\nentry 2\n"));
+        assert_eq!(written.parse::<Program>().unwrap().metadata(), program.metadata());
+
+        let plain = "1,0,0,0,99".parse::<Program>().unwrap();
+
+        assert!(plain.metadata().is_empty());
+        assert_eq!(plain.to_string(), "1,0,0,0,99");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s: &str| s.parse::<Program>().err().unwrap();
+
+        assert_eq!(error("#intcode\nentry x\n").to_string(), "line 2, column 7: invalid entry point `x`");
+        assert_eq!(error("#intcode\n\n  label 1 a\n").to_string(), "line 3, column 3: unknown directive `label`");
+        assert_eq!(error("#intcode\nsource 1 a.asm\n").to_string(), "line 2, column 10: expected file:line, found `a.asm`");
+        assert_eq!(error("#intcode\nsymbol 1").kind, ErrorKind::Missing("symbol name"));
+
+        let err = error("#intcode\ncode 1, x\n");
+
+        assert!(matches!(err.kind, ErrorKind::Number(_)));
+        assert_eq!((err.line, err.column), (2, 9));
+        assert_eq!((error("1,,2").line, error("1,,2").column), (1, 3));
+    }
+}
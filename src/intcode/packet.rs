@@ -0,0 +1,295 @@
+//! Machines that talk by sending `(address, x, y)` packets to each other.
+//!
+//! Every machine is booted with its address as the first input. Afterwards it reads the `x` and
+//! `y` of packets sent to it, or `-1` when its queue is empty. The three values it writes make up
+//! a packet. Packets for addresses outside the network go to a `Monitor`, which is also asked what
+//! to do whenever the whole network is idle.
+
+use std::collections::VecDeque;
+
+use super::{Fault, Process, Program, StopReason, Until};
+
+/// Instructions a machine may execute before the next one gets its turn.
+const SLICE: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Packet {
+    pub address: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Continue,
+    Send(Packet),
+    Stop,
+}
+
+pub trait Monitor {
+    /// Receives a packet addressed outside the network.
+    fn packet(&mut self, packet: Packet) -> Action;
+
+    /// Called when no machine has anything to do. `Continue` here means the network is deadlocked.
+    fn idle(&mut self) -> Action;
+}
+
+/// Keeps the last packet sent to it and sends it to machine 0 whenever the network goes idle,
+/// stopping once it would send the same `y` twice in a row.
+#[derive(Default)]
+pub struct Nat {
+    pub received: Vec<Packet>,
+    pub delivered: Vec<Packet>,
+}
+
+impl Monitor for Nat {
+    fn packet(&mut self, packet: Packet) -> Action {
+        self.received.push(packet);
+        Action::Continue
+    }
+
+    fn idle(&mut self) -> Action {
+        let last = match self.received.last() {
+            Some(last) => *last,
+            None => return Action::Continue,
+        };
+
+        let wake = Packet { address: 0, x: last.x, y: last.y };
+        let repeated = self.delivered.last().is_some_and(|previous| previous.y == wake.y);
+
+        self.delivered.push(wake);
+
+        if repeated {
+            Action::Stop
+        } else {
+            Action::Send(wake)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    /// The monitor asked to stop.
+    Stopped,
+    /// Every machine halted.
+    Halted,
+    /// The network went idle and the monitor had nothing to send.
+    Deadlocked,
+    /// The machine at this address could not execute its next instruction.
+    Faulted(usize, Fault),
+    /// The monitor sent a packet to an address outside the network.
+    Undeliverable(Packet),
+}
+
+struct Machine {
+    process: Process,
+    queue: VecDeque<i64>,
+    pending: Vec<i64>,
+    halted: bool,
+}
+
+pub struct PacketNetwork {
+    machines: Vec<Machine>,
+    idle_periods: usize,
+}
+
+impl PacketNetwork {
+    pub fn new(program: &Program, size: usize) -> PacketNetwork {
+        let machines = (0..size).map(|address| Machine {
+            process: program.spawn(),
+            queue: VecDeque::from(vec![address as i64]),
+            pending: Vec::new(),
+            halted: false,
+        }).collect();
+
+        PacketNetwork { machines, idle_periods: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    pub fn process(&self, address: usize) -> &Process {
+        &self.machines[address].process
+    }
+
+    /// Number of times the whole network went idle so far.
+    pub fn idle_periods(&self) -> usize {
+        self.idle_periods
+    }
+
+    /// Queues a packet for a machine. Packets for unknown addresses are dropped.
+    pub fn send(&mut self, packet: Packet) -> bool {
+        match self.machines.get_mut(packet.address as usize).filter(|_| packet.address >= 0) {
+            Some(machine) => {
+                machine.queue.extend([packet.x, packet.y]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sends a packet inside the network, or hands it to the monitor when it is addressed elsewhere.
+    /// Returns why the network has to stop, if it does.
+    fn route(&mut self, packet: Packet, monitor: &mut dyn Monitor) -> Option<Outcome> {
+        if self.send(packet) {
+            return None;
+        }
+
+        match monitor.packet(packet) {
+            Action::Continue => None,
+            Action::Send(packet) => self.deliver(packet),
+            Action::Stop => Some(Outcome::Stopped),
+        }
+    }
+
+    /// Sends a packet from the monitor, which has nobody to pass it on to when it is addressed
+    /// outside the network.
+    fn deliver(&mut self, packet: Packet) -> Option<Outcome> {
+        if self.send(packet) {
+            None
+        } else {
+            Some(Outcome::Undeliverable(packet))
+        }
+    }
+
+    pub fn run(&mut self, monitor: &mut dyn Monitor) -> Outcome {
+        loop {
+            let mut busy = false;
+
+            for address in 0..self.machines.len() {
+                let machine = &mut self.machines[address];
+
+                if machine.halted {
+                    continue;
+                }
+
+                let queued = machine.queue.len();
+                let mut polled = false;
+                let queue = &mut machine.queue;
+
+                // An empty queue reads as a single -1, after which the machine yields until the next round
+                let mut input = || queue.pop_front().or_else(|| if polled { None } else { polled = true; Some(-1) });
+                let reason = machine.process.run_until_with(Until { budget: Some(SLICE), ..Until::default() }, &mut input, &mut machine.pending).reason;
+
+                if let StopReason::Fault(fault) = reason {
+                    return Outcome::Faulted(address, fault);
+                }
+
+                machine.halted = reason == StopReason::Halted;
+                busy |= reason == StopReason::BudgetExhausted || machine.queue.len() < queued || !machine.pending.is_empty();
+
+                let packets = machine.pending.chunks_exact(3).map(|p| Packet { address: p[0], x: p[1], y: p[2] }).collect::<Vec<_>>();
+                machine.pending.drain(..packets.len() * 3);
+
+                for packet in packets {
+                    if let Some(outcome) = self.route(packet, monitor) {
+                        return outcome;
+                    }
+                }
+            }
+
+            if self.machines.iter().all(|machine| machine.halted) {
+                return Outcome::Halted;
+            }
+
+            if busy || self.machines.iter().any(|machine| !machine.queue.is_empty()) {
+                continue;
+            }
+
+            self.idle_periods += 1;
+
+            match monitor.idle() {
+                Action::Continue => return Outcome::Deadlocked,
+                Action::Send(packet) => {
+                    if let Some(outcome) = self.deliver(packet) {
+                        return outcome;
+                    }
+                }
+                Action::Stop => return Outcome::Stopped,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Monitor, Nat, Outcome, Packet, PacketNetwork};
+    use crate::intcode::Program;
+
+    // Waits for a packet, adds its own address to y and passes it on to the next address
+    const RELAY: &str = "3,100,3,101,1008,101,-1,103,1005,103,2,3,102,1001,100,1,104,1,102,100,102,4,104,4,101,4,102,1105,1,2";
+
+    struct StopAt(i64, Vec<Packet>);
+
+    impl Monitor for StopAt {
+        fn packet(&mut self, packet: Packet) -> Action {
+            self.1.push(packet);
+
+            if packet.y >= self.0 { Action::Stop } else { Action::Send(Packet { address: 0, ..packet }) }
+        }
+
+        fn idle(&mut self) -> Action {
+            Action::Continue
+        }
+    }
+
+    #[test]
+    fn routing() {
+        let program = RELAY.parse::<Program>().unwrap();
+        let mut network = PacketNetwork::new(&program, 4);
+        let mut monitor = StopAt(12, Vec::new());
+
+        network.send(Packet { address: 0, x: 7, y: 0 });
+
+        // Each lap around machines 0 to 3 adds 0 + 1 + 2 + 3
+        assert_eq!(network.run(&mut monitor), Outcome::Stopped);
+        assert_eq!(monitor.1, vec![Packet { address: 4, x: 7, y: 6 }, Packet { address: 4, x: 7, y: 12 }]);
+
+        let mut network = PacketNetwork::new(&program, 4);
+
+        assert_eq!(network.run(&mut StopAt(0, Vec::new())), Outcome::Deadlocked);
+        assert_eq!(network.idle_periods(), 1);
+    }
+
+    #[test]
+    fn undeliverable() {
+        struct Misroute;
+
+        impl Monitor for Misroute {
+            fn packet(&mut self, packet: Packet) -> Action {
+                Action::Send(Packet { address: -1, ..packet })
+            }
+
+            fn idle(&mut self) -> Action {
+                Action::Continue
+            }
+        }
+
+        let program = RELAY.parse::<Program>().unwrap();
+        let mut network = PacketNetwork::new(&program, 2);
+
+        network.send(Packet { address: 0, x: 7, y: 0 });
+
+        assert_eq!(network.run(&mut Misroute), Outcome::Undeliverable(Packet { address: -1, x: 7, y: 1 }));
+    }
+
+    #[test]
+    fn nat() {
+        // A lone machine 0 adds nothing, so the NAT keeps waking it with the same packet
+        let program = RELAY.parse::<Program>().unwrap();
+        let mut network = PacketNetwork::new(&program, 1);
+        let mut nat = Nat::default();
+
+        network.send(Packet { address: 0, x: 3, y: 5 });
+
+        assert_eq!(network.run(&mut nat), Outcome::Stopped);
+        assert_eq!(nat.received.first(), Some(&Packet { address: 1, x: 3, y: 5 }));
+        assert_eq!(nat.delivered, vec![Packet { address: 0, x: 3, y: 5 }; 2]);
+        assert_eq!(network.idle_periods(), 2);
+    }
+}
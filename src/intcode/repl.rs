@@ -0,0 +1,352 @@
+//! Line-oriented REPL for poking at unknown programs. Each command returns its reply as text, so
+//! the same `Repl` can sit behind a terminal or be driven from tests.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use super::disasm::{self, Instruction};
+use super::{Process, Program, StopReason, Until};
+
+const HELP: &str = "\
+load <path>            Load a program and start it
+reset                  Restart the loaded program
+feed <a,b,...>         Queue input values
+out                    Show outputs that have not been shown yet
+regs                   Show eip, rbo and the step count
+peek <addr> [count]    Show memory cells
+poke <addr> <value>    Write a memory cell
+dis [addr] [count]     Disassemble, starting at eip by default
+asm <instruction>      Assemble an instruction at eip, replacing what is there
+exec <instruction>     Execute an instruction at eip without changing the program
+step [n]               Execute n instructions
+run                    Run until the program halts or needs input
+break <addr>           Toggle a breakpoint
+quit                   Leave the REPL";
+
+/// Most cells `peek` and `dis` show at once, so a typo cannot produce a reply of gigabytes.
+const MAX_COUNT: usize = 4096;
+
+#[derive(Default)]
+pub struct Repl {
+    program: Option<Program>,
+    process: Option<Process>,
+    finished: bool,
+}
+
+fn number<T: std::str::FromStr>(arg: Option<&str>, what: &str) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("Missing {}", what))?;
+    arg.parse::<T>().map_err(|_| format!("Invalid {} `{}`", what, arg))
+}
+
+fn count(arg: Option<&str>, default: usize) -> Result<usize, String> {
+    let count = arg.map_or(Ok(default), |c| number::<usize>(Some(c), "count"))?;
+
+    if count > MAX_COUNT {
+        return Err(format!("Count {} is above the limit of {}", count, MAX_COUNT));
+    }
+
+    Ok(count)
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    pub fn with_program(program: Program) -> Repl {
+        let process = program.spawn();
+        Repl { program: Some(program), process: Some(process), finished: false }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Runs one command line and returns what should be shown for it.
+    pub fn execute(&mut self, line: &str) -> String {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        let result = match command {
+            "" => Ok(String::new()),
+            "help" | "?" => Ok(HELP.to_string()),
+            "quit" | "exit" => {
+                self.finished = true;
+                Ok(String::new())
+            }
+            "load" => self.load(rest),
+            _ if self.process.is_none() => Err("No program loaded".to_string()),
+            _ => self.command(command, rest),
+        };
+
+        result.unwrap_or_else(|message| format!("error: {}", message))
+    }
+
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        let program = source.trim().parse::<Program>().map_err(|e| format!("Cannot parse {}: {}", path, e))?;
+        let size = program.code().len();
+
+        *self = Repl::with_program(program);
+        Ok(format!("loaded {} cells", size))
+    }
+
+    fn command(&mut self, command: &str, rest: &str) -> Result<String, String> {
+        let mut args = rest.split_whitespace();
+
+        match command {
+            "reset" => {
+                self.process = self.program.as_ref().map(Program::spawn);
+                Ok("reset".to_string())
+            }
+            "feed" => {
+                let values = rest.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|v| !v.is_empty())
+                    .map(|v| number::<i64>(Some(v), "input"))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                for value in &values {
+                    self.process().feed(*value);
+                }
+
+                Ok(format!("queued {} input(s)", values.len()))
+            }
+            "out" => Ok(self.drain()),
+            "regs" => {
+                let process = self.process();
+                Ok(format!("eip={} rbo={} steps={}", process.eip(), process.rbo(), process.steps()))
+            }
+            "peek" => {
+                let address = number::<usize>(args.next(), "address")?;
+                let count = count(args.next(), 1)?;
+                let end = address.checked_add(count).ok_or_else(|| format!("Range {}+{} is out of bounds", address, count))?;
+                let process = self.process();
+
+                Ok((address..end).map(|a| format!("[{}] = {}", a, process.peek(a))).collect::<Vec<_>>().join("\n"))
+            }
+            "poke" => {
+                let address = number::<usize>(args.next(), "address")?;
+                let value = number::<i64>(args.next(), "value")?;
+
+                self.process().poke(address, value)?;
+                Ok(format!("[{}] = {}", address, value))
+            }
+            "dis" => {
+                let process = self.process();
+                let address = args.next().map_or(Ok(process.eip()), |a| number::<usize>(Some(a), "address"))?;
+                let count = count(args.next(), 5)?;
+
+                Ok(self.disassemble(address, count))
+            }
+            "asm" => {
+                let cells = rest.parse::<Instruction>()?.encode();
+                let process = self.process();
+                let eip = process.eip();
+
+                for (i, cell) in cells.iter().enumerate() {
+                    process.poke(eip + i, *cell)?;
+                }
+
+                Ok(self.disassemble(eip, 1))
+            }
+            "exec" => self.inject(rest.parse::<Instruction>()?),
+            "step" => {
+                let count = args.next().map_or(Ok(1), |c| number::<u64>(Some(c), "count"))?;
+                let reason = self.process().run_until(Until { budget: Some(count), ..Until::default() }).reason;
+
+                Ok(self.report(reason))
+            }
+            "run" | "continue" => {
+                let reason = self.process().run();
+                Ok(self.report(reason))
+            }
+            "break" => {
+                let address = number::<usize>(args.next(), "address")?;
+                let process = self.process();
+
+                if process.has_breakpoint(address) {
+                    process.remove_breakpoint(address);
+                    Ok(format!("breakpoint at {} removed", address))
+                } else {
+                    process.add_breakpoint(address);
+                    Ok(format!("breakpoint at {} added", address))
+                }
+            }
+            _ => Err(format!("Unknown command `{}`, try `help`", command)),
+        }
+    }
+
+    fn process(&mut self) -> &mut Process {
+        self.process.as_mut().expect("No program loaded")
+    }
+
+    /// Runs `instruction` in place of the one at eip, then puts the original cells back. Unless the
+    /// instruction jumped, eip is left where it was so the program continues undisturbed.
+    fn inject(&mut self, instruction: Instruction) -> Result<String, String> {
+        let cells = instruction.encode();
+        let process = self.process();
+        let eip = process.eip();
+        let original = (0..cells.len()).map(|i| process.peek(eip + i)).collect::<Vec<_>>();
+
+        for (i, cell) in cells.iter().enumerate() {
+            process.poke(eip + i, *cell)?;
+        }
+
+        let reason = process.step().reason;
+
+        for (i, cell) in original.iter().enumerate() {
+            if process.peek(eip + i) == cells[i] {
+                process.poke(eip + i, *cell)?;
+            }
+        }
+
+        if process.eip() == eip + cells.len() {
+            process.set_eip(eip);
+        }
+
+        Ok(self.report(reason))
+    }
+
+    fn drain(&mut self) -> String {
+        let process = self.process();
+        let outputs = std::iter::from_fn(|| process.read()).map(|v| v.to_string()).collect::<Vec<_>>();
+
+        outputs.join(",")
+    }
+
+    fn disassemble(&mut self, address: usize, count: usize) -> String {
+        let process = self.process();
+        let mut lines = Vec::new();
+        let mut address = address;
+
+        for _ in 0..count {
+            let (size, text) = match disasm::decode(&*process, address) {
+                Some(instruction) => (instruction.size(), instruction.to_string()),
+                None => (1, format!("data {}", process.peek(address))),
+            };
+
+            let marker = if address == process.eip() { ">" } else { " " };
+            lines.push(format!("{}{:>5}  {}", marker, address, text));
+            address += size;
+        }
+
+        lines.join("\n")
+    }
+
+    fn report(&mut self, reason: StopReason) -> String {
+        let outputs = self.drain();
+        let eip = self.process().eip();
+
+        let status = match reason {
+            StopReason::Halted => format!("halted at {}", eip),
+            StopReason::WaitingForInput => format!("waiting for input at {}", eip),
+            StopReason::Breakpoint => format!("breakpoint at {}", eip),
+            StopReason::MemoryLimit => format!("memory limit at {}", eip),
+            StopReason::OutputClosed => format!("output closed at {}", eip),
+            StopReason::Fault(fault) => format!("{} at {}", fault, eip),
+            StopReason::OutputReady | StopReason::BudgetExhausted => self.disassemble(eip, 1),
+        };
+
+        if outputs.is_empty() {
+            status
+        } else {
+            format!("out: {}\n{}", outputs, status)
+        }
+    }
+}
+
+/// Reads commands from `reader` until `quit` or end of input, prompting on `writer`.
+pub fn serve<R: BufRead, W: Write>(reader: R, mut writer: W, mut repl: Repl) -> io::Result<()> {
+    write!(writer, "intcode> ")?;
+    writer.flush()?;
+
+    for line in reader.lines() {
+        let reply = repl.execute(&line?);
+
+        if !reply.is_empty() {
+            writeln!(writer, "{}", reply)?;
+        }
+
+        if repl.is_finished() {
+            return Ok(());
+        }
+
+        write!(writer, "intcode> ")?;
+        writer.flush()?;
+    }
+
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{serve, Repl};
+    use crate::intcode::Program;
+
+    fn session(repl: &mut Repl, commands: &[(&str, &str)]) {
+        for (command, reply) in commands {
+            assert_eq!(repl.execute(command), *reply, "reply to `{}`", command);
+        }
+    }
+
+    #[test]
+    fn exploring() {
+        let mut repl = Repl::new();
+
+        session(&mut repl, &[
+            ("regs", "error: No program loaded"),
+            ("load src/intcode/transcripts/countdown.txt", "loaded 12 cells"),
+            ("dis 0 3", ">    0  in [100]\n     2  out [100]\n     4  add [100], -1, [100]"),
+            ("run", "waiting for input at 0"),
+            ("feed 2", "queued 1 input(s)"),
+            ("step 2", "out: 2\n>    4  add [100], -1, [100]"),
+            ("peek 100", "[100] = 2"),
+            ("exec add [100], 10, [100]", ">    4  add [100], -1, [100]"),
+            ("peek 100 2", "[100] = 12\n[101] = 0"),
+            ("asm add [100], -4, [100]", ">    4  add [100], -4, [100]"),
+            ("break 2", "breakpoint at 2 added"),
+            ("run", "breakpoint at 2"),
+            ("break 2", "breakpoint at 2 removed"),
+            ("poke 100 4", "[100] = 4"),
+            ("run", "out: 4\nhalted at 11"),
+            ("regs", "eip=11 rbo=0 steps=8"),
+            ("reset", "reset"),
+            ("peek 5", "[5] = 100"),
+            ("asm jmp 4", "error: Unknown mnemonic `jmp`"),
+            ("frobnicate", "error: Unknown command `frobnicate`, try `help`"),
+        ]);
+    }
+
+    #[test]
+    fn faults() {
+        let mut repl = Repl::with_program("1101,1,2,0,99".parse::<Program>().unwrap());
+
+        session(&mut repl, &[
+            ("poke 0 55", "[0] = 55"),
+            ("step", "unknown opcode 55 at 0"),
+            ("poke 0 1301", "[0] = 1301"),
+            ("step", "invalid parameter mode 3 at 0"),
+            ("poke 0 11101", "[0] = 11101"),
+            ("run", "write to an immediate parameter at 0"),
+            ("poke 0 1201", "[0] = 1201"),
+            ("poke 1 -1", "[1] = -1"),
+            ("run", "negative address -1 at 0"),
+            ("peek 18446744073709551615 2", "error: Range 18446744073709551615+2 is out of bounds"),
+            ("peek 0 1000000000000", "error: Count 1000000000000 is above the limit of 4096"),
+            ("dis 0 4097", "error: Count 4097 is above the limit of 4096"),
+            ("poke 1000000000000 1", "error: Address 1000000000000 is out of reach, only 5 cells are in use"),
+            ("regs", "eip=0 rbo=0 steps=0"),
+        ]);
+    }
+
+    #[test]
+    fn prompt() {
+        let program = "104,7,99".parse::<Program>().unwrap();
+        let mut output = Vec::new();
+
+        serve("run\nquit\nregs\n".as_bytes(), &mut output, Repl::with_program(program)).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "intcode> out: 7\nhalted at 2\nintcode> ");
+    }
+}
@@ -0,0 +1,264 @@
+//! Recording and deterministic replay of the values a process exchanges with the outside world.
+//!
+//! A journal lists every value consumed by `in` and produced by `out` together with the step at
+//! which the instruction ran. Its text form has one event per line, such as `in 0 5` or `out 3 10`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Process, Program, StopReason, Until};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    Input { step: u64, value: i64 },
+    Output { step: u64, value: i64 },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Input { step, value } => write!(f, "in {} {}", step, value),
+            Event::Output { step, value } => write!(f, "out {} {}", step, value),
+        }
+    }
+}
+
+impl FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Event, String> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+
+        let (kind, step, value) = match fields[..] {
+            [kind, step, value] => (kind, step, value),
+            _ => return Err(format!("Expected `in|out <step> <value>`, found `{}`", s)),
+        };
+
+        let step = step.parse::<u64>().map_err(|e| format!("Invalid step `{}`: {}", step, e))?;
+        let value = value.parse::<i64>().map_err(|e| format!("Invalid value `{}`: {}", value, e))?;
+
+        match kind {
+            "in" => Ok(Event::Input { step, value }),
+            "out" => Ok(Event::Output { step, value }),
+            _ => Err(format!("Unknown event `{}`", kind)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Journal {
+    pub events: Vec<Event>,
+}
+
+impl Journal {
+    pub fn inputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::Input { value, .. } => Some(*value),
+            _ => None,
+        })
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::Output { value, .. } => Some(*value),
+            _ => None,
+        })
+    }
+
+    /// First event from `from` on where `actual` disagrees with this journal. Events the run has
+    /// not got to yet only count as missing once it is `finished`.
+    pub fn divergence(&self, actual: &[Event], from: usize, finished: bool) -> Option<Divergence> {
+        for index in from.min(actual.len())..actual.len().max(self.events.len()) {
+            match (self.events.get(index), actual.get(index)) {
+                (Some(e), Some(a)) if e == a => {}
+                (Some(_), None) if !finished => break,
+                (expected, actual) => return Some(Divergence { index, expected: expected.copied(), actual: actual.copied() }),
+            }
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for Journal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Journal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Journal, String> {
+        let events = s.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| line.parse::<Event>().map_err(|e| format!("line {}: {}", i + 1, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Journal { events })
+    }
+}
+
+/// First event where the replayed run disagrees with the journal. `None` means the event is missing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub expected: Option<Event>,
+    pub actual: Option<Event>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |event: Option<Event>| event.map_or("nothing".to_string(), |e| format!("`{}`", e));
+
+        write!(f, "Event {} diverges: expected {}, got {}", self.index, show(self.expected), show(self.actual))
+    }
+}
+
+/// Re-runs a program from a journal. The process is exposed so breakpoints can be set and its
+/// state inspected whenever `run` stops, which makes a recorded session debuggable step by step.
+/// It starts with every recorded input queued, so a debugger can also drive it and `verify` what
+/// it did afterwards.
+pub struct Replay {
+    journal: Journal,
+    process: Process,
+    outputs: Vec<i64>,
+    /// Events of the replay already known to match the journal.
+    checked: usize,
+}
+
+impl Replay {
+    pub fn new(program: &Program, journal: Journal) -> Replay {
+        let mut process = program.spawn();
+        process.record();
+
+        for value in journal.inputs() {
+            process.feed(value);
+        }
+
+        Replay { journal, process, outputs: Vec::new(), checked: 0 }
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn process(&self) -> &Process {
+        &self.process
+    }
+
+    pub fn process_mut(&mut self) -> &mut Process {
+        &mut self.process
+    }
+
+    pub fn outputs(&self) -> &[i64] {
+        &self.outputs
+    }
+
+    /// Runs until the process stops on its own, or until it produces an output that does not match
+    /// the journal. Inputs are checked when the next output or stop is reached.
+    pub fn run(&mut self) -> Result<StopReason, Divergence> {
+        loop {
+            let reason = self.process.run_until(Until { outputs: Some(1), ..Until::default() }).reason;
+
+            while let Some(value) = self.process.read() {
+                self.outputs.push(value);
+            }
+
+            self.check(reason == StopReason::Halted || reason == StopReason::WaitingForInput)?;
+
+            if reason != StopReason::OutputReady {
+                return Ok(reason);
+            }
+        }
+    }
+
+    /// Checks whatever the process did since the last check, however it was driven, and returns
+    /// how many events of the journal it has replayed so far.
+    pub fn verify(&mut self) -> Result<usize, Divergence> {
+        self.check(false)?;
+        Ok(self.checked)
+    }
+
+    fn check(&mut self, finished: bool) -> Result<(), Divergence> {
+        let actual = self.process.journal().map_or(&[][..], |j| &j.events[..]);
+
+        if let Some(divergence) = self.journal.divergence(actual, self.checked, finished) {
+            return Err(divergence);
+        }
+
+        self.checked = actual.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Divergence, Event, Journal, Replay};
+    use crate::intcode::{Program, StopReason};
+
+    // Outputs twice its input until it reads a zero
+    const DOUBLER: &str = "3,100,1006,100,14,1002,100,2,100,4,100,1105,1,0,99";
+
+    fn record(source: &str, input: &[i64]) -> Journal {
+        let mut process = source.parse::<Program>().unwrap().spawn();
+        process.record();
+
+        let mut input = input.iter().copied();
+        assert_eq!(process.run_with(&mut || input.next(), &mut |_| ()), StopReason::Halted);
+
+        process.journal().cloned().unwrap()
+    }
+
+    #[test]
+    fn recording() {
+        let journal = record(DOUBLER, &[3, 5, 0]);
+
+        assert_eq!(journal.to_string(), "in 0 3\nout 3 6\nin 5 5\nout 8 10\nin 10 0\n");
+        assert_eq!(journal.to_string().parse::<Journal>(), Ok(journal));
+        assert!("in 1".parse::<Journal>().is_err());
+        assert!("jump 1 2".parse::<Journal>().is_err());
+    }
+
+    #[test]
+    fn faithful_replay() {
+        let program = DOUBLER.parse::<Program>().unwrap();
+        let mut replay = Replay::new(&program, record(DOUBLER, &[3, 5, 0]));
+
+        replay.process_mut().add_breakpoint(9);
+
+        assert_eq!(replay.run(), Ok(StopReason::Breakpoint));
+        assert_eq!(replay.process().peek(100), 6);
+        assert_eq!(replay.run(), Ok(StopReason::Breakpoint));
+        assert_eq!(replay.process().peek(100), 10);
+        assert_eq!(replay.run(), Ok(StopReason::Halted));
+        assert_eq!(replay.outputs(), &[6, 10]);
+    }
+
+    #[test]
+    fn divergence() {
+        // Triples instead of doubling
+        let program = "3,100,1006,100,14,1002,100,3,100,4,100,1105,1,0,99".parse::<Program>().unwrap();
+
+        assert_eq!(Replay::new(&program, record(DOUBLER, &[3, 5, 0])).run(), Err(Divergence {
+            index: 1,
+            expected: Some(Event::Output { step: 3, value: 6 }),
+            actual: Some(Event::Output { step: 3, value: 9 }),
+        }));
+
+        let program = DOUBLER.parse::<Program>().unwrap();
+        let mut journal = record(DOUBLER, &[3, 5, 0]);
+        journal.events.push(Event::Output { step: 11, value: 0 });
+
+        assert_eq!(Replay::new(&program, journal).run(), Err(Divergence {
+            index: 5,
+            expected: Some(Event::Output { step: 11, value: 0 }),
+            actual: None,
+        }));
+    }
+}
@@ -0,0 +1,214 @@
+//! Taint analysis that follows every input value through a running `Process`.
+//!
+//! Each memory cell carries the set of input indices its value depends on. Explicit flows propagate
+//! through arithmetic, comparisons and stores, including the taint of the instruction cells
+//! themselves for self-modifying code and of `rbo` for relative operands. Implicit flows are
+//! tracked by adding the taint of every jump condition and target to a control taint that sticks
+//! to all later writes and outputs. That control taint is never lowered again, which keeps the
+//! result sound at the price of over-approximating code that runs after a branch reconverges.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::disasm::{self, Instruction, Mode, Opcode, Operand};
+use super::{Process, StopReason};
+
+pub type Taint = BTreeSet<usize>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaintedOutput {
+    pub value: i64,
+    pub sources: Taint,
+}
+
+pub struct TaintTracker {
+    process: Process,
+    shadow: HashMap<usize, Taint>,
+    control: Taint,
+    rbo: Taint,
+    fed: usize,
+    consumed: usize,
+    outputs: Vec<TaintedOutput>,
+}
+
+impl TaintTracker {
+    pub fn new(process: Process) -> TaintTracker {
+        TaintTracker {
+            process,
+            shadow: HashMap::new(),
+            control: Taint::new(),
+            rbo: Taint::new(),
+            fed: 0,
+            consumed: 0,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Queues an input and returns the index it is tracked under.
+    pub fn feed(&mut self, value: i64) -> usize {
+        self.process.feed(value);
+        self.fed += 1;
+        self.fed - 1
+    }
+
+    pub fn process(&self) -> &Process {
+        &self.process
+    }
+
+    pub fn outputs(&self) -> &[TaintedOutput] {
+        &self.outputs
+    }
+
+    /// Inputs that the value at `address` currently depends on.
+    pub fn taint_of(&self, address: usize) -> Taint {
+        self.shadow.get(&address).cloned().unwrap_or_default()
+    }
+
+    pub fn run(&mut self) -> StopReason {
+        loop {
+            let eip = self.process.eip();
+            let rbo = self.process.rbo();
+            let instruction = disasm::decode(&self.process, eip);
+
+            match self.process.step().reason {
+                StopReason::BudgetExhausted => {}
+                reason => return reason,
+            }
+
+            if let Some(instruction) = instruction {
+                self.propagate(&instruction, eip, rbo);
+            }
+        }
+    }
+
+    fn address(operand: &Operand, rbo: usize) -> Option<usize> {
+        match operand.mode {
+            Mode::Position => Some(operand.value as usize),
+            Mode::Relative => Some(((rbo as i64) + operand.value) as usize),
+            Mode::Immediate => None,
+        }
+    }
+
+    fn load(&self, operand: &Operand, rbo: usize) -> Taint {
+        let mut taint = match Self::address(operand, rbo) {
+            Some(address) => self.taint_of(address),
+            None => Taint::new(),
+        };
+
+        if operand.mode == Mode::Relative {
+            taint.extend(&self.rbo);
+        }
+
+        taint
+    }
+
+    fn store(&mut self, operand: &Operand, rbo: usize, mut taint: Taint) {
+        if operand.mode == Mode::Relative {
+            taint.extend(&self.rbo);
+        }
+
+        if let Some(address) = Self::address(operand, rbo) {
+            if taint.is_empty() {
+                self.shadow.remove(&address);
+            } else {
+                self.shadow.insert(address, taint);
+            }
+        }
+    }
+
+    fn propagate(&mut self, instruction: &Instruction, eip: usize, rbo: usize) {
+        let mut context = self.control.clone();
+
+        for address in eip..eip + instruction.size() {
+            context.extend(self.taint_of(address));
+        }
+
+        let operands = &instruction.operands;
+
+        match instruction.opcode {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+                let mut taint = context;
+                taint.extend(self.load(&operands[0], rbo));
+                taint.extend(self.load(&operands[1], rbo));
+                self.store(&operands[2], rbo, taint);
+            }
+            Opcode::In => {
+                let mut taint = context;
+                taint.insert(self.consumed);
+                self.consumed += 1;
+                self.store(&operands[0], rbo, taint);
+            }
+            Opcode::Out => {
+                let mut sources = context;
+                sources.extend(self.load(&operands[0], rbo));
+
+                if let Some(value) = self.process.read() {
+                    self.outputs.push(TaintedOutput { value, sources });
+                }
+            }
+            Opcode::Jnz | Opcode::Jz => {
+                let condition = self.load(&operands[0], rbo);
+                let target = self.load(&operands[1], rbo);
+
+                self.control.extend(context);
+                self.control.extend(condition);
+                self.control.extend(target);
+            }
+            Opcode::Arb => {
+                let taint = self.load(&operands[0], rbo);
+
+                self.rbo.extend(context);
+                self.rbo.extend(taint);
+            }
+            Opcode::Hlt => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Taint, TaintTracker, TaintedOutput};
+    use crate::intcode::{Program, StopReason};
+
+    fn track(source: &str, input: &[i64]) -> Vec<TaintedOutput> {
+        let mut tracker = TaintTracker::new(source.parse::<Program>().unwrap().spawn());
+
+        for value in input {
+            tracker.feed(*value);
+        }
+
+        assert_eq!(tracker.run(), StopReason::Halted);
+        tracker.outputs().to_vec()
+    }
+
+    fn taint(indices: &[usize]) -> Taint {
+        indices.iter().copied().collect()
+    }
+
+    #[test]
+    fn explicit_flows() {
+        assert_eq!(track("3,0,3,1,4,1,99", &[5, 6]), vec![TaintedOutput { value: 6, sources: taint(&[1]) }]);
+        assert_eq!(track("3,0,3,1,1,0,1,2,4,2,104,7,99", &[5, 6]), vec![
+            TaintedOutput { value: 11, sources: taint(&[0, 1]) },
+            TaintedOutput { value: 7, sources: taint(&[]) },
+        ]);
+    }
+
+    #[test]
+    fn overwritten_cells_lose_taint() {
+        assert_eq!(track("3,0,1101,1,2,0,4,0,99", &[5]), vec![TaintedOutput { value: 3, sources: taint(&[]) }]);
+    }
+
+    #[test]
+    fn implicit_flows() {
+        let program = "3,0,1005,0,8,104,0,99,104,1,99";
+
+        assert_eq!(track(program, &[0]), vec![TaintedOutput { value: 0, sources: taint(&[0]) }]);
+        assert_eq!(track(program, &[1]), vec![TaintedOutput { value: 1, sources: taint(&[0]) }]);
+    }
+
+    #[test]
+    fn relative_base() {
+        // The output cell is chosen by the first input through `arb`
+        assert_eq!(track("3,100,9,100,3,101,204,100,99", &[1, 8]), vec![TaintedOutput { value: 8, sources: taint(&[0, 1]) }]);
+    }
+}
@@ -0,0 +1,413 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+#[macro_export]
+macro_rules! json_object {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        $crate::json::Value::Object(vec![$(($key.to_string(), $crate::json::Value::from($value))),*])
+    };
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Adds or replaces `key` on an object, ignoring any other kind of value.
+    pub fn insert<V: Into<Value>>(&mut self, key: &str, value: V) {
+        if let Value::Object(entries) = self {
+            let value = value.into();
+
+            match entries.iter_mut().find(|(k, _)| k == key) {
+                Some(entry) => entry.1 = value,
+                None => entries.push((key.to_string(), value)),
+            }
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Value {
+        Value::Int(n as i64)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Int(n)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(n: u32) -> Value {
+        Value::Int(n as i64)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Value {
+        Value::Int(n as i64)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Value {
+        Value::Int(n as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Float(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Value {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(n) if n.is_finite() => write!(f, "{:?}", n),
+            Value::Float(_) => write!(f, "null"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) => {
+                write!(f, "[")?;
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}", item)?;
+                }
+
+                write!(f, "]")
+            }
+            Value::Object(entries) => {
+                write!(f, "{{")?;
+
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError { offset: self.pos, message }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), ParseError> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(self.error("Unexpected token"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            None => Err(self.error("Unexpected end of input")),
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("Unexpected character")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+
+        while let Some(b'-') | Some(b'+') | Some(b'.') | Some(b'e') | Some(b'E') | Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+
+        let text = &self.input[start..self.pos];
+
+        if let Ok(n) = text.parse::<i64>() {
+            return Ok(Value::Int(n));
+        }
+
+        text.parse::<f64>().map(Value::Float).map_err(|_| ParseError { offset: start, message: "Invalid number" })
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let digits = self.input.get(self.pos..self.pos + 4).ok_or_else(|| self.error("Invalid unicode escape"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("Invalid unicode escape"))?;
+
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect("\"")?;
+
+        let mut result = String::new();
+
+        loop {
+            let c = self.input[self.pos..].chars().next().ok_or_else(|| self.error("Unterminated string"))?;
+            self.pos += c.len_utf8();
+
+            match c {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("Unterminated string"))?;
+                    self.pos += 1;
+
+                    match escape {
+                        b'"' => result.push('"'),
+                        b'\\' => result.push('\\'),
+                        b'/' => result.push('/'),
+                        b'b' => result.push('\u{8}'),
+                        b'f' => result.push('\u{c}'),
+                        b'n' => result.push('\n'),
+                        b'r' => result.push('\r'),
+                        b't' => result.push('\t'),
+                        b'u' => {
+                            let mut code = self.hex4()?;
+
+                            // Characters outside the basic plane arrive as a surrogate pair
+                            if (0xd800..0xdc00).contains(&code) {
+                                self.expect("\\u")?;
+                                let low = self.hex4()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+
+                            result.push(std::char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))?);
+                        }
+                        _ => return Err(self.error("Invalid escape")),
+                    }
+                }
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.expect("[")?;
+        self.skip_whitespace();
+
+        let mut items = Vec::new();
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => { self.pos += 1; return Ok(Value::Array(items)); }
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, ParseError> {
+        self.expect("{")?;
+        self.skip_whitespace();
+
+        let mut entries = Vec::new();
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(":")?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => { self.pos += 1; return Ok(Value::Object(entries)); }
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Value, ParseError> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let value = parser.value()?;
+
+        parser.skip_whitespace();
+
+        if parser.pos != s.len() {
+            return Err(parser.error("Trailing characters"));
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Value;
+
+    #[test]
+    fn roundtrip() {
+        let source = r#"{"seq":1,"ok":true,"none":null,"pi":3.5,"list":[1,-2,"three"],"nested":{"text":"a\"b\\c\nd"}}"#;
+        let value = source.parse::<Value>().unwrap();
+
+        assert_eq!(value.get("seq"), Some(&Value::Int(1)));
+        assert_eq!(value.get("list").and_then(|l| l.as_array()).map(|l| l.len()), Some(3));
+        assert_eq!(value.get("nested").and_then(|n| n.get("text")).and_then(|t| t.as_str()), Some("a\"b\\c\nd"));
+        assert_eq!(value.to_string(), source);
+    }
+
+    #[test]
+    fn whitespace_and_escapes() {
+        let value = " { \"a\" : [ ] , \"b\" : \"\\u00e9\\ud83d\\ude00\" } ".parse::<Value>().unwrap();
+
+        assert_eq!(value, json_object! { "a" => Vec::<Value>::new(), "b" => "é😀" });
+    }
+
+    #[test]
+    fn errors() {
+        assert!("{\"a\":}".parse::<Value>().is_err());
+        assert!("[1,2".parse::<Value>().is_err());
+        assert!("\"open".parse::<Value>().is_err());
+        assert!("1 2".parse::<Value>().is_err());
+    }
+}
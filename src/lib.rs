@@ -1,4 +1,39 @@
-use aoc_runner_derive::aoc_lib;
+//! Solutions to Advent of Code 2019, usable as a library as well as through the `advent-of-code`
+//! binary.
+//!
+//! Every day has a `part1` and a `part2` that take the puzzle input as text, and `solvers` lists
+//! them all for the runner. Days with something worth reusing also expose their model:
+//!
+//! - `day3`: wires as an `InstructionSet` or a `Path` of segments, and where two of them cross.
+//! - `day6`: the orbit graph as a `Map` of object `Id`s.
+//! - `day10`: the asteroid field as a `Map`, with lines of sight and the vaporization order.
+//! - `day11`: the painted `Hull`, and `paint` to run a robot program over one.
+//! - `intcode`: the Intcode computer.
+//!
+//! Parsers report problems as an `error::ParseError` pointing at the line and column.
+//!
+//! ```
+//! use advent_of_code::day3::{Path, Wires};
+//! use advent_of_code::day6::{Id, Map};
+//! use advent_of_code::intcode::Program;
+//!
+//! let wires = "R8,U5,L5,D3\nU7,R6,D4,L4".parse::<Wires>()?;
+//! let crossings = Path::from(wires.first).crossings(&Path::from(wires.second));
+//!
+//! assert_eq!(crossings.iter().map(|point| point.manhattan_distance()).min(), Some(6));
+//!
+//! let map = "COM)B\nB)C\nC)YOU\nB)SAN".parse::<Map>()?;
+//!
+//! assert_eq!(map.transfers(Id::new("YOU"), Id::new("SAN")), Some(1));
+//!
+//! let mut process = "3,0,4,0,99".parse::<Program>()?.spawn();
+//!
+//! process.feed(42);
+//! process.run();
+//!
+//! assert_eq!(process.read(), Some(42));
+//! # Ok::<(), advent_of_code::error::ParseError>(())
+//! ```
 
 pub mod day1;
 pub mod day2;
@@ -12,4 +47,96 @@ pub mod day9;
 pub mod day10;
 pub mod day11;
 
-aoc_lib!{ year = 2019 }
+pub mod bench;
+pub mod cli;
+pub mod combinatorics;
+pub mod error;
+pub mod generate;
+pub mod intcode;
+pub mod json;
+pub mod parallel;
+pub mod runner;
+pub mod scaffold;
+pub mod svg;
+
+use runner::Solver;
+
+/// Every registered solver, ordered by day and part with the main implementation first.
+pub fn solvers() -> Vec<Solver> {
+    vec![
+        solver!(1, 1, None, day1::part1),
+        solver!(1, 2, None, day1::part2),
+        solver!(2, 1, None, day2::part1),
+        solver!(2, 2, None, day2::part2),
+        solver!(3, 1, None, day3::closest_crossing),
+        solver!(3, 2, None, day3::quickest_crossing),
+        solver!(4, 1, None, day4::part1),
+        solver!(4, 2, None, day4::part2),
+        solver!(5, 1, None, day5::part1),
+        solver!(5, 2, None, day5::part2),
+        solver!(6, 1, None, day6::part1),
+        solver!(6, 2, None, day6::part2),
+        solver!(7, 1, None, day7::best_chain),
+        solver!(7, 1, Some("serial"), day7::part1_serial),
+        solver!(7, 2, None, day7::best_feedback_loop),
+        solver!(7, 2, Some("serial"), day7::part2_serial),
+        solver!(8, 1, None, day8::part1),
+        solver!(8, 2, None, day8::part2),
+        solver!(9, 1, None, day9::part1),
+        solver!(9, 2, None, day9::part2),
+        solver!(10, 1, None, day10::station),
+        solver!(10, 2, None, day10::vaporization),
+        solver!(11, 1, None, day11::part1),
+        solver!(11, 2, None, day11::part2),
+    ]
+}
+
+pub type Renderer = fn(&str) -> Result<svg::Svg, runner::Failure>;
+
+/// The days that can draw their input as SVG.
+pub fn renderers() -> Vec<(u32, Renderer)> {
+    vec![
+        (3, |input| Ok(day3::render(input)?)),
+        (6, |input| Ok(day6::render(input)?)),
+        (10, |input| Ok(day10::render(input)?)),
+        (11, |input| Ok(day11::render(input)?)),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::runner::{self, Expected, DEFAULT_INPUT_DIR};
+
+    #[test]
+    fn answers() {
+        let dir = Path::new(DEFAULT_INPUT_DIR);
+        let expected = Expected::read(dir).unwrap();
+        let mut failures = Vec::new();
+
+        for solver in super::solvers() {
+            for input in runner::inputs(dir, solver.day).unwrap() {
+                let text = input.read().unwrap();
+
+                // A new day before its puzzle input was pasted in
+                if text.is_empty() {
+                    continue;
+                }
+
+                let mut outcome = runner::run(&solver, &text);
+                let name = format!("day{} part{} {} [{}]", solver.day, solver.part, solver.name.unwrap_or("default"), input.name());
+
+                outcome.profile = input.profile;
+
+                match expected.check(&outcome) {
+                    Some(Ok(())) => {}
+                    Some(Err(message)) => failures.push(format!("{}: {}", name, message)),
+                    None => failures.push(format!("{}: no recorded answer", name)),
+                }
+            }
+        }
+
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
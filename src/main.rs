@@ -1,3 +1,426 @@
-use aoc_runner_derive::aoc_main;
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 
-aoc_main! { lib = advent_of_code }
+use advent_of_code::bench::{self, Baseline, CountingAllocator};
+use advent_of_code::cli::parse_value;
+use advent_of_code::json::Value;
+use advent_of_code::runner::{self, Expected, Input, Solver, DEFAULT_INPUT_DIR};
+use advent_of_code::scaffold;
+
+const USAGE: &str = "\
+Usage: advent-of-code [options]
+       advent-of-code new <day> [--intcode]
+
+Options:
+    --day <n,...>      Only run these days, may be repeated
+    --part <n>         Only run this part
+    --impl <name>      Run the named alternative implementation instead of the main one, or `all`
+    --input <path>     Read the input from this file, only with a single day
+    --input-dir <dir>  Read inputs from <dir> instead of input/2019
+    --profile <name,...>
+                       Only run these profiles from <dir>/dayN/<name>.txt, `default` being <dir>/dayN.txt
+    --format <format>  Print `text` (default) or `json`
+    --check            Compare the answers with <dir>/answers.txt and fail on any difference
+    --list             List the registered solvers instead of running them
+    --svg <dir>        Draw the inputs of days 3, 6, 10 and 11 to <dir>/dayN.svg instead
+
+Benchmarking:
+    --bench                Time the parse and solve phases of every solver instead of printing answers
+    --iterations <n>       Timed runs per solver, 10 by default
+    --save-baseline <path> Write the measurements as JSON
+    --baseline <path>      Compare with saved measurements and fail on regressions
+    --threshold <percent>  Slowdown of the median tolerated against the baseline, 10 by default
+
+New day, run from the crate root:
+    new <day>              Create src/dayN.rs with stubs, register it in src/lib.rs and create an empty input
+    --intcode              Parse the input of the new day as an Intcode program";
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+enum Format {
+    Text,
+    Json,
+}
+
+struct Options {
+    days: BTreeSet<u32>,
+    part: Option<u32>,
+    implementation: Option<String>,
+    input: Option<PathBuf>,
+    input_dir: PathBuf,
+    profiles: BTreeSet<String>,
+    format: Format,
+    check: bool,
+    list: bool,
+    svg: Option<PathBuf>,
+    bench: bool,
+    iterations: usize,
+    save_baseline: Option<PathBuf>,
+    baseline: Option<PathBuf>,
+    threshold: f64,
+    help: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            days: BTreeSet::new(),
+            part: None,
+            implementation: None,
+            input: None,
+            input_dir: PathBuf::from(DEFAULT_INPUT_DIR),
+            profiles: BTreeSet::new(),
+            format: Format::Text,
+            check: false,
+            list: false,
+            svg: None,
+            bench: false,
+            iterations: 10,
+            save_baseline: None,
+            baseline: None,
+            threshold: 10.0,
+            help: false,
+        };
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--day" => {
+                    let days = args.next().ok_or("Missing value for --day")?;
+
+                    for day in days.split(',') {
+                        options.days.insert(day.trim().parse().map_err(|_| format!("Invalid value for --day: {}", day))?);
+                    }
+                }
+                "--part" => options.part = Some(parse_value(args.next(), "--part")?),
+                "--impl" => options.implementation = Some(args.next().ok_or("Missing value for --impl")?.clone()),
+                "--input" => options.input = Some(PathBuf::from(args.next().ok_or("Missing value for --input")?)),
+                "--input-dir" => options.input_dir = PathBuf::from(args.next().ok_or("Missing value for --input-dir")?),
+                "--profile" => {
+                    let profiles = args.next().ok_or("Missing value for --profile")?;
+
+                    options.profiles.extend(profiles.split(',').map(|profile| profile.trim().to_string()));
+                }
+                "--format" => {
+                    options.format = match args.next().map(String::as_str) {
+                        Some("text") => Format::Text,
+                        Some("json") => Format::Json,
+                        Some(format) => return Err(format!("Unknown format {}", format)),
+                        None => return Err("Missing value for --format".to_string()),
+                    }
+                }
+                "--check" => options.check = true,
+                "--list" => options.list = true,
+                "--svg" => options.svg = Some(PathBuf::from(args.next().ok_or("Missing value for --svg")?)),
+                "--bench" => options.bench = true,
+                "--iterations" => options.iterations = parse_value(args.next(), "--iterations")?,
+                "--save-baseline" => options.save_baseline = Some(PathBuf::from(args.next().ok_or("Missing value for --save-baseline")?)),
+                "--baseline" => options.baseline = Some(PathBuf::from(args.next().ok_or("Missing value for --baseline")?)),
+                "--threshold" => options.threshold = parse_value(args.next(), "--threshold")?,
+                "help" | "--help" | "-h" => {
+                    options.help = true;
+                    return Ok(options);
+                }
+                _ => return Err(format!("Unknown option {}\n\n{}", arg, USAGE)),
+            }
+        }
+
+        if options.input.is_some() && options.days.len() != 1 {
+            return Err("--input needs exactly one --day".to_string());
+        }
+
+        if options.input.is_some() && !options.profiles.is_empty() {
+            return Err("--profile picks inputs of --input-dir and cannot be used with --input".to_string());
+        }
+
+        if options.input.is_some() && options.check {
+            return Err("--check compares against the inputs of --input-dir and cannot be used with --input".to_string());
+        }
+
+        Ok(options)
+    }
+
+    fn selects(&self, solver: &Solver) -> bool {
+        let implementation = match (self.implementation.as_deref(), solver.name) {
+            (None, name) | (Some("default"), name) => name.is_none(),
+            (Some("all"), _) => true,
+            (Some(wanted), name) => name == Some(wanted),
+        };
+
+        implementation && (self.days.is_empty() || self.days.contains(&solver.day)) && self.part.is_none_or(|part| part == solver.part)
+    }
+}
+
+fn list(solvers: &[&Solver], format: &Format) {
+    match format {
+        Format::Text => {
+            for solver in solvers {
+                println!("day{} part{} {}", solver.day, solver.part, solver.name.unwrap_or("default"));
+            }
+        }
+        Format::Json => {
+            let solvers = solvers.iter().map(|solver| advent_of_code::json_object! {
+                "day" => solver.day,
+                "part" => solver.part,
+                "name" => solver.name,
+            }).collect::<Vec<_>>();
+
+            println!("{}", Value::from(solvers));
+        }
+    }
+}
+
+/// The selected inputs of a day, which are all of its profiles unless `--profile` picks some.
+fn inputs(day: u32, options: &Options) -> Result<Vec<Input>, String> {
+    if let Some(path) = &options.input {
+        return Ok(vec![Input { day, profile: None, path: path.clone() }]);
+    }
+
+    let inputs = runner::inputs(&options.input_dir, day)
+        .map_err(|e| format!("Cannot list the inputs of day {} in {}: {}", day, options.input_dir.display(), e))?;
+
+    Ok(inputs.into_iter().filter(|input| options.profiles.is_empty() || options.profiles.contains(input.name())).collect())
+}
+
+/// Pairs every selected input with the solvers of its day, relying on solvers being ordered by day.
+/// Days without any of the selected profiles are left out.
+fn runs<'a>(solvers: &[&'a Solver], options: &Options) -> Result<Vec<(Input, Vec<&'a Solver>)>, String> {
+    let mut runs = Vec::new();
+
+    for day in solvers.chunk_by(|lhs, rhs| lhs.day == rhs.day) {
+        for input in inputs(day[0].day, options)? {
+            runs.push((input, day.to_vec()));
+        }
+    }
+
+    if runs.is_empty() {
+        return Err("No input matches the selected profiles".to_string());
+    }
+
+    Ok(runs)
+}
+
+fn run(solvers: &[&Solver], options: &Options) -> Result<(), String> {
+    let mut outcomes = Vec::new();
+    let mut failures = Vec::new();
+    let expected = if options.check { Some(Expected::read(&options.input_dir)?) } else { None };
+    let runs = runs(solvers, options)?;
+
+    if let Format::Text = options.format {
+        println!("Advent of code 2019");
+    }
+
+    for (input, solvers) in runs {
+        let text = input.read()?;
+
+        // A new day before its puzzle input was pasted in
+        if text.is_empty() {
+            continue;
+        }
+
+        for solver in solvers {
+            let mut outcome = runner::run(solver, &text);
+
+            outcome.profile = input.profile.clone();
+
+            let verdict = expected.as_ref().map(|expected| expected.check(&outcome).unwrap_or_else(|| Err("no recorded answer".to_string())));
+
+            if let Some(Err(message)) = &verdict {
+                failures.push(format!("Day {} - Part {} - {} [{}]: {}", solver.day, solver.part, solver.name.unwrap_or("default"), input.name(), message));
+            }
+
+            match options.format {
+                Format::Text => println!("{}", outcome),
+                Format::Json => {
+                    let mut outcome = outcome.to_json();
+
+                    if let Some(verdict) = verdict {
+                        outcome.insert("correct", verdict.is_ok());
+                    }
+
+                    outcomes.push(outcome);
+                }
+            }
+        }
+    }
+
+    if let Format::Json = options.format {
+        println!("{}", Value::from(outcomes));
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("\n"))
+    }
+}
+
+fn render(dir: &Path, options: &Options) -> Result<(), String> {
+    let renderers = advent_of_code::renderers().into_iter().filter(|(day, _)| options.days.is_empty() || options.days.contains(day)).collect::<Vec<_>>();
+
+    if renderers.is_empty() {
+        return Err("None of the selected days can be drawn".to_string());
+    }
+
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+
+    for (day, render) in renderers {
+        for input in inputs(day, options)? {
+            let text = input.read()?;
+
+            // A new day before its puzzle input was pasted in
+            if text.is_empty() {
+                continue;
+            }
+
+            let svg = render(&text).map_err(|e| format!("Cannot draw {}: {}", input.path.display(), e))?;
+            let output = match &input.profile {
+                Some(profile) => dir.join(format!("day{}-{}.svg", day, profile)),
+                None => dir.join(format!("day{}.svg", day)),
+            };
+
+            fs::write(&output, svg.to_string()).map_err(|e| format!("Cannot write {}: {}", output.display(), e))?;
+            println!("{}", output.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn benchmark(solvers: &[&Solver], options: &Options) -> Result<(), String> {
+    let baseline = match &options.baseline {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            let value = text.parse::<Value>().map_err(|e| format!("Cannot parse {}: {}", path.display(), e))?;
+
+            Some(Baseline::from_json(&value)?)
+        }
+        None => None,
+    };
+
+    let mut measurements = Vec::new();
+    let mut regressions = Vec::new();
+
+    for (input, solvers) in runs(solvers, options)? {
+        let text = input.read()?;
+        let mut measured = Vec::new();
+
+        if text.is_empty() {
+            continue;
+        }
+
+        for solver in solvers {
+            let mut measurement = bench::measure(solver, &text, options.iterations)
+                .map_err(|e| format!("Day {} - Part {} [{}]: {}", solver.day, solver.part, input.name(), e))?;
+
+            measurement.profile = input.profile.clone();
+
+            if let Some(baseline) = &baseline {
+                regressions.extend(baseline.regressions(&measurement, options.threshold / 100.0));
+            }
+
+            measured.push(measurement);
+        }
+
+        for measurement in &measured {
+            if let Format::Text = options.format {
+                print!("{}", measurement);
+
+                // Alternative solvers, such as the serial ones, are there to be compared against the default
+                let default = measured.iter().find(|other| other.part == measurement.part && other.name.is_none());
+
+                if let (Some(_), Some(default)) = (measurement.name, default) {
+                    println!("\tdefault solves {:.2}x as fast", measurement.relative_to(default));
+                }
+
+                println!();
+            }
+
+            measurements.push(measurement.to_json());
+        }
+    }
+
+    let measurements = Value::from(measurements);
+
+    if let Format::Json = options.format {
+        println!("{}", measurements);
+    }
+
+    if let Some(path) = &options.save_baseline {
+        fs::write(path, format!("{}\n", measurements)).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+
+    if regressions.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Regressions against the baseline:\n{}", regressions.join("\n")))
+    }
+}
+
+fn new_day(args: &[String]) -> Result<(), String> {
+    let (day, intcode) = match args {
+        [flag] if matches!(flag.as_str(), "help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        [day] => (day, false),
+        [day, flag] if flag == "--intcode" => (day, true),
+        _ => return Err(USAGE.to_string()),
+    };
+    let day = parse_value(Some(day), "new")?;
+
+    for path in scaffold::scaffold(Path::new("."), day, intcode)? {
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    if args.first().map(String::as_str) == Some("new") {
+        if let Err(message) = new_day(&args[1..]) {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    let result = Options::parse(&args).and_then(|options| {
+        if options.help {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+
+        let solvers = advent_of_code::solvers();
+        let selected = solvers.iter().filter(|solver| options.selects(solver)).collect::<Vec<_>>();
+
+        if selected.is_empty() {
+            return Err("No solver matches the selection".to_string());
+        }
+
+        if let Some(dir) = &options.svg {
+            return render(dir, &options);
+        }
+
+        if options.list {
+            list(&selected, &options.format);
+            Ok(())
+        } else if options.bench {
+            benchmark(&selected, &options)
+        } else {
+            run(&selected, &options)
+        }
+    });
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,121 @@
+use std::ops::Range;
+use std::sync::Mutex;
+use std::thread;
+
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Takes the next index from the worker's own range, or steals the upper half of the largest
+/// range left when it runs dry. Only one lock is held at a time.
+fn next(queues: &[Mutex<Range<usize>>], id: usize) -> Option<usize> {
+    if let Some(index) = queues[id].lock().unwrap().next() {
+        return Some(index);
+    }
+
+    loop {
+        let victim = (0..queues.len()).max_by_key(|&i| queues[i].lock().unwrap().len())?;
+
+        let stolen = {
+            let mut range = queues[victim].lock().unwrap();
+
+            if range.is_empty() {
+                return None;
+            }
+
+            let middle = range.start + range.len() / 2;
+            let stolen = middle..range.end;
+            range.end = middle;
+            stolen
+        };
+
+        let mut own = queues[id].lock().unwrap();
+        *own = stolen;
+
+        if let Some(index) = own.next() {
+            return Some(index);
+        }
+    }
+}
+
+/// Evaluates every item on `threads` workers and returns the index and key of the best one, the
+/// lowest index winning ties. Each worker builds its own state with `init` once and hands it to
+/// every evaluation, so buffers can be reused between items.
+pub fn best_by_key<T, S, K, I, F>(items: &[T], threads: usize, init: I, evaluate: F) -> Option<(usize, K)>
+where
+    T: Sync,
+    K: Ord + Send,
+    I: Fn() -> S + Sync,
+    F: Fn(&mut S, &T) -> K + Sync,
+{
+    let threads = threads.clamp(1, items.len().max(1));
+    let chunk = items.len().div_ceil(threads);
+    let queues = (0..threads).map(|i| Mutex::new((i * chunk).min(items.len())..((i + 1) * chunk).min(items.len()))).collect::<Vec<_>>();
+
+    let better = |a: &(usize, K), b: &(usize, K)| a.1 > b.1 || (a.1 == b.1 && a.0 < b.0);
+
+    thread::scope(|scope| {
+        let workers = (0..threads).map(|id| {
+            let (queues, init, evaluate) = (&queues, &init, &evaluate);
+
+            scope.spawn(move || {
+                let mut state = init();
+                let mut best: Option<(usize, K)> = None;
+
+                while let Some(index) = next(queues, id) {
+                    let candidate = (index, evaluate(&mut state, &items[index]));
+
+                    if best.as_ref().is_none_or(|best| better(&candidate, best)) {
+                        best = Some(candidate);
+                    }
+                }
+
+                best
+            })
+        }).collect::<Vec<_>>();
+
+        workers.into_iter().filter_map(|worker| worker.join().unwrap()).fold(None, |best, candidate| match best {
+            Some(best) if !better(&candidate, &best) => Some(best),
+            _ => Some(candidate),
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::best_by_key;
+
+    #[test]
+    fn matches_serial() {
+        let items = (0..1000).map(|i| (i * 7919) % 1013).collect::<Vec<i64>>();
+        let serial = items.iter().enumerate().max_by_key(|(i, v)| (**v, -(*i as i64))).map(|(i, v)| (i, *v));
+
+        for threads in [1, 2, 3, 8, 64] {
+            assert_eq!(best_by_key(&items, threads, || (), |_, v| *v), serial);
+        }
+
+        assert_eq!(best_by_key(&[] as &[i64], 4, || (), |_, v| *v), None);
+    }
+
+    #[test]
+    fn uneven_work() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // The first worker's share is far slower, so the others have to steal from it
+        let items = (0..64).collect::<Vec<u64>>();
+        let evaluated = AtomicUsize::new(0);
+
+        let best = best_by_key(&items, 4, Vec::new, |seen: &mut Vec<u64>, item| {
+            if *item < 16 {
+                std::thread::sleep(std::time::Duration::from_millis(2));
+            }
+
+            seen.push(*item);
+            evaluated.fetch_add(1, Ordering::SeqCst);
+            item % 10
+        });
+
+        assert_eq!(best, Some((9, 9)));
+        assert_eq!(evaluated.load(Ordering::SeqCst), 64);
+    }
+}
@@ -0,0 +1,392 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::json::Value;
+use crate::json_object;
+
+pub type Generated = Box<dyn Any>;
+pub type Answer = Box<dyn Solution>;
+pub type Failure = Box<dyn Error>;
+
+/// What a solver returns. The display is the answer to submit and the report, if any, holds the
+/// intermediate values that led to it.
+pub trait Solution: fmt::Display {
+    fn report(&self) -> Option<Value> {
+        None
+    }
+}
+
+impl Solution for i64 {}
+impl Solution for u64 {}
+impl Solution for usize {}
+
+/// One way of solving one part of a day. The generator turns the puzzle input into whatever the
+/// runner works on, which lets the two phases be timed separately.
+pub struct Solver {
+    pub day: u32,
+    pub part: u32,
+    /// `None` for the main implementation, otherwise the name of an alternative.
+    pub name: Option<&'static str>,
+    pub generator: fn(&str) -> Result<Generated, Failure>,
+    pub runner: fn(&dyn Any) -> Result<Answer, Failure>,
+}
+
+/// Registers a part function. Without a generator the function receives the raw input as `&str`,
+/// with one it receives a reference to whatever the generator returned.
+#[macro_export]
+macro_rules! solver {
+    ($day:expr, $part:expr, $name:expr, $generator:path => $runner:path) => {
+        $crate::runner::Solver {
+            day: $day,
+            part: $part,
+            name: $name,
+            generator: |input| Ok(Box::new($generator(input)?)),
+            runner: |input| Ok(Box::new($runner(input.downcast_ref().expect("Generator output has the wrong type"))?)),
+        }
+    };
+    ($day:expr, $part:expr, $name:expr, $runner:path) => {
+        $crate::runner::Solver {
+            day: $day,
+            part: $part,
+            name: $name,
+            generator: |input| Ok(Box::new(input.to_string())),
+            runner: |input| Ok(Box::new($runner(input.downcast_ref::<String>().expect("Generator output has the wrong type"))?)),
+        }
+    };
+}
+
+pub const DEFAULT_INPUT_DIR: &str = "input/2019";
+
+pub fn input_path(dir: &Path, day: u32) -> PathBuf {
+    dir.join(format!("day{}.txt", day))
+}
+
+/// One puzzle input of a day. Besides the default `dir/dayN.txt`, a day can have named profiles in
+/// `dir/dayN/<profile>.txt` for everyone with their own puzzle data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Input {
+    pub day: u32,
+    pub profile: Option<String>,
+    pub path: PathBuf,
+}
+
+impl Input {
+    pub fn read(&self) -> Result<String, String> {
+        read_input(&self.path).map_err(|e| format!("Cannot read {}: {}", self.path.display(), e))
+    }
+
+    /// The profile name, with `default` for `dayN.txt`.
+    pub fn name(&self) -> &str {
+        self.profile.as_deref().unwrap_or("default")
+    }
+}
+
+/// Every input of a day, the default one first and then the profiles by name. When there is none
+/// at all the default one is still returned, so that reading it reports what is missing.
+pub fn inputs(dir: &Path, day: u32) -> io::Result<Vec<Input>> {
+    let default = Input { day, profile: None, path: input_path(dir, day) };
+    let mut profiles = Vec::new();
+
+    match fs::read_dir(dir.join(format!("day{}", day))) {
+        Ok(entries) => {
+            for entry in entries {
+                let path = entry?.path();
+
+                if path.extension().is_some_and(|extension| extension == "txt") {
+                    if let Some(profile) = path.file_stem().and_then(|stem| stem.to_str()) {
+                        profiles.push(Input { day, profile: Some(profile.to_string()), path: path.clone() });
+                    }
+                }
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    profiles.sort_by(|lhs, rhs| lhs.profile.cmp(&rhs.profile));
+
+    if default.path.is_file() || profiles.is_empty() {
+        profiles.insert(0, default);
+    }
+
+    Ok(profiles)
+}
+
+pub fn answers_path(dir: &Path) -> PathBuf {
+    dir.join("answers.txt")
+}
+
+/// Reads a puzzle input without its trailing newlines.
+pub fn read_input<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    fs::read_to_string(path).map(|input| input.trim_end_matches('\n').to_string())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    Generating,
+    Running,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Generating => write!(f, "generating"),
+            Stage::Running => write!(f, "running"),
+        }
+    }
+}
+
+pub struct Outcome {
+    pub day: u32,
+    pub part: u32,
+    pub name: Option<&'static str>,
+    /// Set by the caller when the input came from a profile.
+    pub profile: Option<String>,
+    pub answer: Result<String, (Stage, String)>,
+    pub report: Option<Value>,
+    pub generator: Duration,
+    pub runner: Duration,
+}
+
+pub fn run(solver: &Solver, input: &str) -> Outcome {
+    let start = Instant::now();
+    let generated = (solver.generator)(input);
+    let generator = start.elapsed();
+
+    let start = Instant::now();
+    let solution = match generated {
+        Ok(generated) => (solver.runner)(&*generated).map_err(|err| (Stage::Running, err.to_string())),
+        Err(err) => Err((Stage::Generating, err.to_string())),
+    };
+    let runner = start.elapsed();
+
+    let report = solution.as_ref().ok().and_then(|solution| solution.report());
+    let answer = solution.map(|solution| solution.to_string());
+
+    Outcome { day: solver.day, part: solver.part, name: solver.name, profile: None, answer, report, generator, runner }
+}
+
+impl Outcome {
+    pub fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "day" => self.day,
+            "part" => self.part,
+            "name" => self.name,
+            "profile" => self.profile.clone(),
+            "generator_ns" => self.generator.as_nanos() as u64,
+            "runner_ns" => self.runner.as_nanos() as u64,
+        };
+
+        match &self.answer {
+            Ok(answer) => value.insert("answer", answer.as_str()),
+            Err((stage, message)) => value.insert("error", json_object! { "stage" => stage.to_string(), "message" => message.as_str() }),
+        }
+
+        if let Some(report) = &self.report {
+            value.insert("report", report.clone());
+        }
+
+        value
+    }
+}
+
+/// Same layout as the reports of `cargo aoc`.
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Day {} - Part {}", self.day, self.part)?;
+
+        if let Some(name) = self.name {
+            write!(f, " - {}", name)?;
+        }
+
+        if let Some(profile) = &self.profile {
+            write!(f, " [{}]", profile)?;
+        }
+
+        match &self.answer {
+            Ok(answer) => writeln!(f, ": {}\n\tgenerator: {:?},\n\trunner: {:?}", answer, self.generator, self.runner),
+            Err((stage, message)) => writeln!(f, ": FAILED while {}: {}", stage, message),
+        }
+    }
+}
+
+/// Day, part and profile of an answer.
+type Key = (u32, u32, Option<String>);
+
+/// The known answers for a directory of inputs, shared by every implementation of a part.
+#[derive(Debug, Default)]
+pub struct Expected {
+    answers: BTreeMap<Key, String>,
+}
+
+impl Expected {
+    pub fn read(dir: &Path) -> Result<Expected, String> {
+        let path = answers_path(dir);
+        let text = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+
+        text.parse().map_err(|e| format!("Cannot parse {}: {}", path.display(), e))
+    }
+
+    pub fn get(&self, day: u32, part: u32, profile: Option<&str>) -> Option<&str> {
+        self.answers.get(&(day, part, profile.map(str::to_string))).map(String::as_str)
+    }
+
+    /// Compares an outcome with the recorded answer, if there is one. Leading and trailing newlines
+    /// are ignored on both sides.
+    pub fn check(&self, outcome: &Outcome) -> Option<Result<(), String>> {
+        let expected = self.get(outcome.day, outcome.part, outcome.profile.as_deref())?;
+
+        Some(match &outcome.answer {
+            Ok(answer) if answer.trim_matches('\n') == expected => Ok(()),
+            Ok(answer) => Err(format!("expected\n{}\nbut got\n{}", expected, answer.trim_matches('\n'))),
+            Err((stage, _)) => Err(format!("failed while {}", stage)),
+        })
+    }
+}
+
+/// Sections start with a `[dayN partM]` header, or `[dayN partM profile]` for a profile, and run
+/// until the next one. Lines starting with `#` before the first section are comments.
+impl FromStr for Expected {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Expected, String> {
+        let mut expected = Expected::default();
+        let mut current: Option<(Key, Vec<&str>)> = None;
+
+        let mut finish = |current: Option<(Key, Vec<&str>)>| {
+            if let Some((key, lines)) = current {
+                expected.answers.insert(key, lines.join("\n").trim_matches('\n').to_string());
+            }
+        };
+
+        for (number, line) in s.lines().enumerate() {
+            if let Some(header) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let key = header.strip_prefix("day")
+                    .and_then(|header| header.split_once(" part"))
+                    .and_then(|(day, rest)| {
+                        let (part, profile) = match rest.split_once(' ') {
+                            Some((part, profile)) => (part, Some(profile.to_string())),
+                            None => (rest, None),
+                        };
+
+                        Some((day.parse().ok()?, part.parse().ok()?, profile))
+                    })
+                    .ok_or_else(|| format!("line {}: invalid section {}", number + 1, line))?;
+
+                finish(current.replace((key, Vec::new())));
+            } else if let Some((_, lines)) = current.as_mut() {
+                lines.push(line);
+            } else if !line.is_empty() && !line.starts_with('#') {
+                return Err(format!("line {}: answer outside of a section", number + 1));
+            }
+        }
+
+        finish(current);
+
+        Ok(expected)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::ParseIntError;
+
+    use std::fs;
+
+    use super::{inputs, run, Expected, Input, Stage};
+
+    struct Numbers(Vec<i64>);
+
+    fn parse(input: &str) -> Result<Numbers, ParseIntError> {
+        input.lines().map(str::parse).collect::<Result<_, _>>().map(Numbers)
+    }
+
+    fn sum(numbers: &Numbers) -> Result<i64, ParseIntError> {
+        Ok(numbers.0.iter().sum())
+    }
+
+    fn length(input: &str) -> Result<usize, ParseIntError> {
+        Ok(input.len())
+    }
+
+    #[test]
+    fn generators() {
+        let outcome = run(&solver!(1, 2, None, parse => sum), "1\n2\n3");
+
+        assert_eq!(outcome.answer, Ok("6".to_string()));
+        assert!(outcome.to_string().starts_with("Day 1 - Part 2: 6\n\tgenerator: "));
+
+        let outcome = run(&solver!(1, 2, Some("raw"), length), "1\n2\n3");
+
+        assert_eq!(outcome.answer, Ok("5".to_string()));
+        assert!(outcome.to_string().starts_with("Day 1 - Part 2 - raw: 5\n"));
+        assert_eq!(outcome.to_json().get("name").and_then(|n| n.as_str()), Some("raw"));
+    }
+
+    #[test]
+    fn failures() {
+        let outcome = run(&solver!(3, 1, None, parse => sum), "1\nx");
+
+        assert!(matches!(outcome.answer, Err((Stage::Generating, _))));
+        assert_eq!(outcome.to_string(), "Day 3 - Part 1: FAILED while generating: invalid digit found in string\n");
+        assert_eq!(outcome.to_json().get("error").and_then(|e| e.get("stage")).and_then(|s| s.as_str()), Some("generating"));
+    }
+
+    #[test]
+    fn expected() {
+        let expected = "# comment\n\n[day1 part2]\n6\n\n[day8 part2]\n\n# \n #\n\n".parse::<Expected>().unwrap();
+
+        assert_eq!(expected.get(1, 2, None), Some("6"));
+        assert_eq!(expected.get(8, 2, None), Some("# \n #"));
+        assert_eq!(expected.get(1, 1, None), None);
+
+        assert_eq!(expected.check(&run(&solver!(1, 2, None, parse => sum), "1\n2\n3")), Some(Ok(())));
+        assert!(matches!(expected.check(&run(&solver!(1, 2, None, parse => sum), "1\n2")), Some(Err(_))));
+        assert_eq!(expected.check(&run(&solver!(1, 1, None, parse => sum), "1")), None);
+
+        let expected = "[day1 part2]\n6\n[day1 part2 alice]\n3".parse::<Expected>().unwrap();
+        let mut outcome = run(&solver!(1, 2, None, parse => sum), "1\n2");
+
+        assert_eq!(expected.get(1, 2, Some("alice")), Some("3"));
+        assert!(matches!(expected.check(&outcome), Some(Err(_))));
+
+        outcome.profile = Some("alice".to_string());
+
+        assert_eq!(expected.check(&outcome), Some(Ok(())));
+        assert!(outcome.to_string().starts_with("Day 1 - Part 2 [alice]: 3\n"));
+        assert_eq!(expected.check(&{ outcome.profile = Some("bob".to_string()); outcome }), None);
+
+        assert!("6".parse::<Expected>().is_err());
+        assert!("[day1]\n6".parse::<Expected>().is_err());
+    }
+
+    #[test]
+    fn profiles() {
+        let dir = std::env::temp_dir().join(format!("advent-of-code-profiles-{}", std::process::id()));
+
+        fs::create_dir_all(dir.join("day5")).unwrap();
+        fs::write(dir.join("day5/bob.txt"), "2\n").unwrap();
+        fs::write(dir.join("day5/alice.txt"), "1\n").unwrap();
+        fs::write(dir.join("day5/notes.md"), "").unwrap();
+
+        let profiles = inputs(&dir, 5).unwrap();
+
+        assert_eq!(profiles.iter().map(Input::name).collect::<Vec<_>>(), ["alice", "bob"]);
+        assert_eq!(profiles[1].read(), Ok("2".to_string()));
+
+        fs::write(dir.join("day5.txt"), "0").unwrap();
+
+        assert_eq!(inputs(&dir, 5).unwrap().iter().map(Input::name).collect::<Vec<_>>(), ["default", "alice", "bob"]);
+        assert_eq!(inputs(&dir, 6).unwrap(), [Input { day: 6, profile: None, path: dir.join("day6.txt") }]);
+        assert!(inputs(&dir, 6).unwrap()[0].read().unwrap_err().starts_with("Cannot read "));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
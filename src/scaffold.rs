@@ -0,0 +1,236 @@
+//! Starts a new day: a module with a generator, two part stubs and ignored example tests, its
+//! registration in `lib.rs` and an empty input file to paste the puzzle input into.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::runner;
+
+const MODULE: &str = r#"use std::str::FromStr;
+
+use crate::error::{parse_lines, ErrorKind, ParseError};
+
+pub struct Puzzle {
+    lines: Vec<String>,
+}
+
+impl FromStr for Puzzle {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Puzzle, ParseError> {
+        parse_lines(s, |line| Ok(line.to_string())).map(|lines| Puzzle { lines })
+    }
+}
+
+pub fn parse(input: &str) -> Result<Puzzle, ParseError> {
+    input.parse()
+}
+
+pub fn part1(puzzle: &Puzzle) -> Result<usize, ParseError> {
+    Err(ParseError::new(ErrorKind::Invalid(format!("day {day} part 1 is not solved yet, {} lines", puzzle.lines.len()))))
+}
+
+pub fn part2(puzzle: &Puzzle) -> Result<usize, ParseError> {
+    Err(ParseError::new(ErrorKind::Invalid(format!("day {day} part 2 is not solved yet, {} lines", puzzle.lines.len()))))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[ignore]
+    fn part1() {
+        assert_eq!(super::part1(&super::parse("").unwrap()), Ok(0));
+    }
+
+    #[test]
+    #[ignore]
+    fn part2() {
+        assert_eq!(super::part2(&super::parse("").unwrap()), Ok(0));
+    }
+}
+"#;
+
+const INTCODE_MODULE: &str = r#"use crate::error::{ErrorKind, ParseError};
+use crate::intcode::Program;
+
+pub fn parse(input: &str) -> Result<Program, ParseError> {
+    input.parse()
+}
+
+pub fn part1(program: &Program) -> Result<i64, ParseError> {
+    let mut process = program.spawn();
+
+    process.run();
+
+    Err(ParseError::new(ErrorKind::Invalid(format!("day {day} part 1 is not solved yet, the program wrote {:?}", process.read()))))
+}
+
+pub fn part2(program: &Program) -> Result<i64, ParseError> {
+    let mut process = program.spawn();
+
+    process.run();
+
+    Err(ParseError::new(ErrorKind::Invalid(format!("day {day} part 2 is not solved yet, the program wrote {:?}", process.read()))))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[ignore]
+    fn part1() {
+        assert_eq!(super::part1(&super::parse("99").unwrap()), Ok(0));
+    }
+
+    #[test]
+    #[ignore]
+    fn part2() {
+        assert_eq!(super::part2(&super::parse("99").unwrap()), Ok(0));
+    }
+}
+"#;
+
+/// The source of a new day, parsing its input into lines or, with `intcode`, into a program.
+pub fn module(day: u32, intcode: bool) -> String {
+    let template = if intcode { INTCODE_MODULE } else { MODULE };
+
+    template.replace("{day}", &day.to_string())
+}
+
+/// Day of a line like `pub mod day7;` or `solver!(7, 1, ...)`.
+fn day_of(line: &str) -> Option<u32> {
+    let line = line.trim();
+
+    line.strip_prefix("pub mod day").and_then(|rest| rest.strip_suffix(';'))
+        .or_else(|| line.strip_prefix("solver!(").and_then(|rest| rest.split(',').next()))
+        .and_then(|day| day.parse().ok())
+}
+
+/// Inserts `new` after the last line that `earlier` accepts, or before the first line that
+/// `later` accepts when there is none, with the same indentation as that line.
+fn insert(lines: &mut Vec<String>, new: &[String], earlier: impl Fn(&str) -> bool, later: impl Fn(&str) -> bool) -> bool {
+    let (neighbour, index) = match lines.iter().rposition(|line| earlier(line)) {
+        Some(index) => (index, index + 1),
+        None => match lines.iter().position(|line| later(line)) {
+            Some(index) => (index, index),
+            None => return false,
+        },
+    };
+    let indent = lines[neighbour][..lines[neighbour].len() - lines[neighbour].trim_start().len()].to_string();
+
+    for (offset, line) in new.iter().enumerate() {
+        lines.insert(index + offset, format!("{}{}", indent, line));
+    }
+
+    true
+}
+
+/// Adds the module and its two solvers to the source of `lib.rs`, right after the previous day or,
+/// for the first one, right before the next day.
+pub fn register(lib: &str, day: u32) -> Result<String, String> {
+    let module = format!("pub mod day{};", day);
+
+    if lib.lines().any(|line| line.trim() == module) {
+        return Err(format!("Day {} is already registered in lib.rs", day));
+    }
+
+    let mut lines = lib.lines().map(str::to_string).collect::<Vec<_>>();
+    let earlier = |line: &str| day_of(line).is_some_and(|other| other < day);
+    let module_line = |line: &str| line.starts_with("pub mod day");
+    let solver_line = |line: &str| line.trim().starts_with("solver!(");
+    let solvers = [
+        format!("solver!({}, 1, None, day{}::parse => day{}::part1),", day, day, day),
+        format!("solver!({}, 2, None, day{}::parse => day{}::part2),", day, day, day),
+    ];
+
+    if !insert(&mut lines, &[module], |line| module_line(line) && earlier(line), |line| module_line(line) && day_of(line).is_some())
+        || !insert(&mut lines, &solvers, |line| solver_line(line) && earlier(line), |line| solver_line(line) && day_of(line).is_some())
+    {
+        return Err(format!("lib.rs has no other day to add day {} next to", day));
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Creates `src/dayN.rs` and an empty input below `root`, and registers the day in `lib.rs`.
+/// Nothing is written when the day already exists. Returns the files it created or changed.
+pub fn scaffold(root: &Path, day: u32, intcode: bool) -> Result<Vec<PathBuf>, String> {
+    let source = root.join("src").join(format!("day{}.rs", day));
+    let lib = root.join("src").join("lib.rs");
+    let input = runner::input_path(&root.join(runner::DEFAULT_INPUT_DIR), day);
+
+    if source.exists() {
+        return Err(format!("{} already exists", source.display()));
+    }
+
+    let text = fs::read_to_string(&lib).map_err(|e| format!("Cannot read {}: {}", lib.display(), e))?;
+    let registered = register(&text, day)?;
+    let write = |path: &Path, contents: &str| fs::write(path, contents).map_err(|e| format!("Cannot write {}: {}", path.display(), e));
+
+    write(&source, &module(day, intcode))?;
+    write(&lib, &registered)?;
+
+    let mut changed = vec![source, lib];
+
+    if !input.exists() {
+        write(&input, "")?;
+        changed.push(input);
+    }
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    const LIB: &str = "\
+pub mod day1;
+pub mod day2;
+pub mod day4;
+
+pub mod runner;
+
+pub fn solvers() -> Vec<Solver> {
+    vec![
+        solver!(1, 1, None, day1::part1),
+        solver!(2, 1, None, day2::part1),
+        solver!(2, 1, Some(\"fast\"), day2::part1_fast),
+        solver!(2, 2, None, day2::part2),
+        solver!(4, 1, None, day4::part1),
+    ]
+}
+";
+
+    #[test]
+    fn register() {
+        let lib = super::register(LIB, 3).unwrap();
+
+        assert!(lib.contains("pub mod day2;\npub mod day3;\npub mod day4;\n"));
+        assert!(lib.contains("day2::part2),\n        solver!(3, 1, None, day3::parse => day3::part1),\n        solver!(3, 2, None, day3::parse => day3::part2),\n        solver!(4, 1,"));
+        assert!(super::register(&super::register(LIB, 12).unwrap(), 12).unwrap_err().contains("already registered"));
+        assert!(super::register(LIB, 1).unwrap_err().contains("already registered"));
+
+        let without_day1 = LIB.replace("pub mod day1;\n", "").replace("        solver!(1, 1, None, day1::part1),\n", "");
+        let lib = super::register(&without_day1, 1).unwrap();
+
+        assert!(lib.starts_with("pub mod day1;\npub mod day2;\n"));
+        assert!(lib.contains("vec![\n        solver!(1, 1, None, day1::parse => day1::part1),\n        solver!(1, 2, None, day1::parse => day1::part2),\n        solver!(2, 1,"));
+        assert!(super::register("pub mod runner;\n", 1).unwrap_err().contains("no other day"));
+    }
+
+    #[test]
+    fn scaffold() {
+        let root = std::env::temp_dir().join(format!("advent-of-code-scaffold-{}", std::process::id()));
+
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("input/2019")).unwrap();
+        fs::write(root.join("src/lib.rs"), LIB).unwrap();
+
+        assert_eq!(super::scaffold(&root, 5, true).unwrap().len(), 3);
+        assert!(fs::read_to_string(root.join("src/day5.rs")).unwrap().contains("\"day 5 part 1 is not solved yet, the program wrote {:?}\""));
+        assert_eq!(fs::read_to_string(root.join("input/2019/day5.txt")).unwrap(), "");
+        assert!(super::scaffold(&root, 5, false).unwrap_err().ends_with("day5.rs already exists"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+//! Just enough SVG to draw the spatial puzzles. Elements are drawn in the order they are added, and
+//! styles are passed as raw attributes like `fill="red" stroke-width="0.5"`.
+
+use std::fmt;
+
+/// Pixels on the longest side when the drawing is shown at its natural size.
+const SIZE: f64 = 800.0;
+
+pub struct Svg {
+    min: (f64, f64),
+    size: (f64, f64),
+    elements: Vec<String>,
+}
+
+/// Coordinates with at most three decimals, so float noise stays out of the file.
+fn number(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl Svg {
+    /// A drawing of the area that starts at `min` and spans `size` user units.
+    pub fn new(min: (f64, f64), size: (f64, f64)) -> Svg {
+        Svg { min, size: (size.0.max(1.0), size.1.max(1.0)), elements: Vec::new() }
+    }
+
+    /// The area around every point, leaving `margin` times the longer side free on each side.
+    pub fn around<I: IntoIterator<Item = (f64, f64)>>(points: I, margin: f64) -> Svg {
+        let (lo, hi) = points.into_iter().fold(((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)), |(lo, hi), (x, y)| {
+            ((lo.0.min(x), lo.1.min(y)), (hi.0.max(x), hi.1.max(y)))
+        });
+
+        if lo.0 > hi.0 {
+            return Svg::new((0.0, 0.0), (1.0, 1.0));
+        }
+
+        let margin = margin * (hi.0 - lo.0).max(hi.1 - lo.1).max(1.0);
+
+        Svg::new((lo.0 - margin, lo.1 - margin), (hi.0 - lo.0 + 2.0 * margin, hi.1 - lo.1 + 2.0 * margin))
+    }
+
+    /// The longer side of the drawing, for sizing strokes and markers.
+    pub fn extent(&self) -> f64 {
+        self.size.0.max(self.size.1)
+    }
+
+    pub fn title(&mut self, text: &str) {
+        self.elements.push(format!("<title>{}</title>", escape(text)));
+    }
+
+    /// Fills the whole drawing, so should come before anything else.
+    pub fn background(&mut self, fill: &str) {
+        self.rect(self.min, self.size, &format!(r#"fill="{}""#, fill));
+    }
+
+    pub fn rect(&mut self, corner: (f64, f64), size: (f64, f64), style: &str) {
+        self.elements.push(format!(r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#, number(corner.0), number(corner.1), number(size.0), number(size.1), style));
+    }
+
+    pub fn circle(&mut self, center: (f64, f64), radius: f64, style: &str) {
+        self.elements.push(format!(r#"<circle cx="{}" cy="{}" r="{}" {}/>"#, number(center.0), number(center.1), number(radius), style));
+    }
+
+    pub fn line(&mut self, from: (f64, f64), to: (f64, f64), style: &str) {
+        self.elements.push(format!(r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#, number(from.0), number(from.1), number(to.0), number(to.1), style));
+    }
+
+    pub fn polyline(&mut self, points: &[(f64, f64)], style: &str) {
+        let points = points.iter().map(|(x, y)| format!("{},{}", number(*x), number(*y))).collect::<Vec<_>>().join(" ");
+
+        self.elements.push(format!(r#"<polyline points="{}" fill="none" {}/>"#, points, style));
+    }
+
+    pub fn text(&mut self, position: (f64, f64), text: &str, style: &str) {
+        self.elements.push(format!(r#"<text x="{}" y="{}" {}>{}</text>"#, number(position.0), number(position.1), style, escape(text)));
+    }
+}
+
+impl fmt::Display for Svg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = SIZE / self.extent();
+
+        writeln!(f, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{:.0}" height="{:.0}">"#,
+            number(self.min.0), number(self.min.1), number(self.size.0), number(self.size.1), self.size.0 * scale, self.size.1 * scale)?;
+
+        for element in &self.elements {
+            writeln!(f, "  {}", element)?;
+        }
+
+        writeln!(f, "</svg>")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Svg;
+
+    #[test]
+    fn document() {
+        let mut svg = Svg::around(vec![(0.0, 0.0), (4.0, 2.0)], 0.25);
+
+        svg.title("a < b & c");
+        svg.polyline(&[(0.0, 0.0), (4.0, 2.0)], r#"stroke="red""#);
+
+        assert_eq!(svg.to_string(), "\
+<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -1 6 4\" width=\"800\" height=\"533\">
+  <title>a &lt; b &amp; c</title>
+  <polyline points=\"0,0 4,2\" fill=\"none\" stroke=\"red\"/>
+</svg>
+");
+        assert_eq!(Svg::around(Vec::new(), 0.25).extent(), 1.0);
+        assert_eq!([super::number(-5.0200000000000005), super::number(2.0), super::number(-0.0001)], ["-5.02", "2", "0"]);
+    }
+}